@@ -1,35 +1,236 @@
 use crate::lex::*;
+use crate::mem::Mem;
+use crate::error::{Error, Span};
+use crate::op::{ArgKind, Sig};
+
+// Pre-decoded operand of a compiled instruction
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Arg{
+    Num(f64),  // immediate
+    Idx(isize),  // direct slot: [n]
+    Var(usize),  // var slot: $name
+    Ind(isize, usize),  // n-level indirect from slot: [[n]]
+    VarInd(usize, usize),  // n-level indirect from var: [$name], [[$name]]
+    Ltl(isize),  // interned literal in nmem
+    Lbl(usize),  // label target
+    Sym(usize),  // offset into symbol pool
+}
+
+impl Arg{
+    pub fn to_type_str(self) -> &'static str{
+        match self {
+            Arg::Num(_) => Tok::NUM_STR,
+            Arg::Idx(_) | Arg::Ind(..) | Arg::VarInd(..) => Tok::IDX_STR,
+            Arg::Var(_) => Tok::VAR_STR,
+            Arg::Ltl(_) => Tok::LTL_STR,
+            Arg::Lbl(_) | Arg::Sym(_) => Tok::SYM_STR,
+        }
+    }
+
+    // lower a token whose idx has been resolved by replace_sym
+    // Sym is lowered according to the kind of arg in op signature
+    fn from_tok(kind: ArgKind, t: &Tok, m: &mut Mem) -> Result<Arg, Error>{
+        Ok(match t {
+            Tok::Num(f) => Arg::Num(*f),
+            Tok::Idx(i) => {
+                let mut idx = i;
+                let mut layer = 0usize;
+                while let Idx::Idx(a) = idx {
+                    idx = a;
+                    layer += 1;
+                }
+                match idx {
+                    Idx::Num(n) if layer == 0 => Arg::Idx(*n),
+                    Idx::Num(n) => Arg::Ind(*n, layer),
+                    Idx::Var(v) => Arg::VarInd(v.idx, layer+1),
+                    Idx::Idx(_) => unreachable!(),
+                }
+            },
+            Tok::Var(v) => Arg::Var(v.idx),
+            Tok::Ltl(s) => {
+                m.ltl_reserve(s)?;
+                Arg::Ltl(m.ltl_intern(s))
+            },
+            Tok::Sym(hi) => match kind {
+                ArgKind::Lbl =>
+                    Arg::Lbl(hi.idx),
+                ArgKind::VarName =>
+                    Arg::Var(hi.idx),
+                _ =>
+                    Arg::Sym(m.sym_add(&hi.sym)),
+            },
+            Tok::Eof => return Err(Error::EmptyToken),
+        })
+    }
+
+    pub fn get_value(&self, m: &Mem) -> Result<f64, Error>{
+        match *self {
+            Arg::Num(f) => Ok(f),
+            Arg::Idx(i) => m.mem_at(i),
+            Arg::Var(v) => m.mem_at(m.var_find(v)?),
+            Arg::Ind(i, layer) => m.mem_at(deref(m, i, layer)?),
+            Arg::VarInd(v, layer) => m.mem_at(deref(m, m.var_find(v)?, layer)?),
+            _ =>
+                Err(Error::WrongArgType(
+                        vec![Tok::NUM_STR, Tok::IDX_STR, Tok::VAR_STR],
+                        self.to_type_str())),
+        }
+    }
+
+    pub fn get_uint(&self, m: &Mem) -> Result<u64, Error>{
+        let float = self.get_value(m)?;
+        if float != (float as u64) as f64 {
+            return Err(Error::NegativeOrNotInterger(float));
+        }
+        Ok(float as u64)
+    }
+
+    pub fn get_int(&self, m: &Mem) -> Result<i64, Error>{
+        let float = self.get_value(m)?;
+        if float != (float as i64) as f64 {
+            return Err(Error::NotInterger(float));
+        }
+        Ok(float as i64)
+    }
+
+    pub fn get_loc(&self, m: &Mem) -> Result<isize, Error> {
+        match *self {
+            Arg::Idx(i) => Ok(i),
+            Arg::Var(v) => m.var_find(v),
+            Arg::Ind(i, layer) => deref(m, i, layer),
+            Arg::VarInd(v, layer) => deref(m, m.var_find(v)?, layer),
+            Arg::Ltl(i) => Ok(i),
+            _ =>
+                Err(Error::WrongArgType(
+                        vec![Tok::IDX_STR, Tok::VAR_STR, Tok::LTL_STR],
+                        self.to_type_str())),
+        }
+    }
+
+    pub fn write_value(&self, m: &mut Mem, v: f64) -> Result<(), Error> {
+        let idx = self.get_loc(m)?;
+        if idx < 0 {
+            return Err(Error::WriteToNMem(idx));
+        }
+        m.mem_set(idx, v)
+    }
+
+    pub fn get_var(&self) -> Result<usize, Error> {
+        if let Arg::Var(v) = *self {
+            Ok(v)
+        }else{
+            Err(Error::WrongArgType(
+                    vec![Tok::VAR_STR],
+                    self.to_type_str()))
+        }
+    }
+
+    pub fn get_lbl(&self) -> Result<usize, Error> {
+        if let Arg::Lbl(l) = *self {
+            Ok(l)
+        }else{
+            Err(Error::WrongArgType(
+                    vec![Tok::SYM_STR],
+                    self.to_type_str()))
+        }
+    }
+
+    pub fn get_sym(&self) -> Result<usize, Error> {
+        if let Arg::Sym(s) = *self {
+            Ok(s)
+        }else{
+            Err(Error::WrongArgType(
+                    vec![Tok::SYM_STR],
+                    self.to_type_str()))
+        }
+    }
+}
+
+// follow pointers stored in slots, starting from slot i
+fn deref(m: &Mem, i: isize, layer: usize) -> Result<isize, Error> {
+    let mut l = i;
+    for _ in 0..layer {
+        let d = m.mem_at(l)?;
+        if d != d as isize as f64 {
+            return Err(Error::NotInterger(d));
+        }
+        l = d as isize;
+    }
+    Ok(l)
+}
+
+// Compiled line. Args are stored in Code::args[argv..argv+argc]
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Inst{
+    pub op: usize,  // index into op table
+    pub argv: usize,
+    pub argc: usize,
+}
+
+// Source of a line, kept for error messages
+#[derive(Clone, Default, Debug)]
+pub struct LineSrc{
+    pub line: usize,  // starts from 1
+    pub text: String,
+    pub cols: Vec<usize>,  // byte offset of each token
+}
+
+impl LineSrc{
+    pub fn span(&self, file: &str, tok: usize) -> Span{
+        Span {
+            file: file.to_owned(),
+            line: self.line,
+            col: self.cols.get(tok).copied().unwrap_or(0),
+            text: self.text.clone(),
+        }
+    }
+}
 
 pub struct Code{
     code: Vec<Vec<Tok>>,
-    func_idx: Vec<usize>,
+    src: Vec<LineSrc>,
+    file: String,
+    inst: Vec<Inst>,
+    args: Vec<Arg>,
     ptr: usize,
 }
 
+impl Default for Code{
+    fn default() -> Self {
+        Code::new()
+    }
+}
+
 impl Code{
     pub fn new() -> Code{
         Code {
             code: Vec::with_capacity(10000),
-            func_idx: Vec::with_capacity(10000),
+            src: Vec::with_capacity(10000),
+            file: String::new(),
+            inst: Vec::with_capacity(10000),
+            args: Vec::with_capacity(30000),
             ptr: 0,
         }
     }
-    pub fn push(&mut self, c: Vec<Tok>) -> usize{
+    pub fn push(&mut self, c: Vec<Tok>, src: LineSrc) -> usize{
         if self.code.len() + c.len() > self.code.capacity(){
             self.code.reserve(10000);
         }
         self.code.push(c);
+        self.src.push(src);
         self.code.len()
     }
-    pub fn func_idx_push(&mut self, idx: usize) -> usize{
-        self.func_idx.push(idx);
-        idx
+    pub fn file(&self) -> &str{
+        &self.file
     }
-    pub fn at(&self, i: usize) -> Option<&[Tok]>{
-        if i >= self.code.len() {
-            None
-        }else{
-            Some(&self.code[i])
+    pub fn set_file(&mut self, file: &str){
+        self.file = file.to_owned();
+    }
+    // location of tok-th token of line i
+    pub fn span(&self, i: usize, tok: usize) -> Span{
+        match self.src.get(i) {
+            Some(s) => s.span(&self.file, tok),
+            None => LineSrc::default().span(&self.file, tok),
         }
     }
     pub fn at_mut(&mut self, i: usize) -> Option<&mut Vec<Tok>>{
@@ -39,23 +240,53 @@ impl Code{
             Some(&mut self.code[i])
         }
     }
-    pub fn last(&self) -> Option<&[Tok]>{
-        if self.code.len() == 0 {
-            None
-        }else{
-            Some(&self.code[self.code.len()-1])
-        }
+    // drop lines from len on, with their instructions and args
+    pub fn truncate(&mut self, len: usize){
+        self.code.truncate(len);
+        self.src.truncate(len);
+        self.inst.truncate(len);
+        let argc = self.inst.last().map_or(0, |i| i.argv+i.argc);
+        self.args.truncate(argc);
     }
-    pub fn curr(&self) -> Option<&[Tok]>{
-        if self.ptr >= self.len() {
-            None
-        }else {
-            Some(&self.code[self.ptr])
+    // lower lines pushed since last compile into instructions
+    pub fn compile(&mut self, m: &mut Mem, op_sig: &[Sig]) -> Result<(), Error>{
+        for (i, line) in self.code.iter().enumerate().skip(self.inst.len()) {
+            let op = if let Tok::Sym(ref hi) = line[0] {
+                hi.idx
+            }else{
+                return Err(Error::WrongTokTypeForOp(line[0].to_type_str())
+                    .at(self.src[i].span(&self.file, 0)));
+            };
+            let argv = self.args.len();
+            for (pos, t) in line[1..].iter().enumerate() {
+                let kind = op_sig[op].get(pos).copied().unwrap_or(ArgKind::Sym);
+                let a = Arg::from_tok(kind, t, m)
+                    .map_err(|e| e.at(self.src[i].span(&self.file, pos+1)))?;
+                self.args.push(a);
+            }
+            self.inst.push(Inst {
+                op,
+                argv,
+                argc: line.len()-1,
+            });
         }
+        Ok(())
+    }
+    pub fn inst_at(&self, i: usize) -> Option<Inst>{
+        self.inst.get(i).copied()
+    }
+    pub fn args(&self, i: &Inst) -> &[Arg]{
+        &self.args[i.argv..i.argv+i.argc]
+    }
+    pub fn curr(&self) -> Option<Inst>{
+        self.inst_at(self.ptr)
     }
     pub fn len(&self) -> usize{
         self.code.len()
     }
+    pub fn is_empty(&self) -> bool{
+        self.code.is_empty()
+    }
     pub fn ptr(&self) -> usize{
         self.ptr
     }
@@ -66,3 +297,6 @@ impl Code{
         self.ptr += 1;
     }
 }
+
+#[cfg(test)]
+mod test;
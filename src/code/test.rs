@@ -0,0 +1,110 @@
+use super::*;
+use crate::Error;
+
+#[test]
+fn read_value(){
+    let t = Arg::Num(10.0);
+    let mut m = Mem::new();
+    m.pmem_allc(&[0.0]);
+    assert_eq!(t.get_value(&m).unwrap(), 10f64);
+    let t = Arg::Idx(1);
+    m.pmem_set(1, 10.0).unwrap();
+    assert_eq!(t.get_value(&m).unwrap(), 10f64);
+    let t = Arg::Var(0);
+    m.var_add(1);
+    assert_eq!(t.get_value(&m).unwrap(), 10f64);
+}
+
+#[test]
+fn read_value_indirect(){
+    let mut m = Mem::new();
+    m.pmem_allc(&[0.0; 2]);
+    for (i, v) in [2.0, 3.0, 7.0].iter().enumerate() {
+        m.pmem_set(i+1, *v).unwrap();
+    }
+    // [[1]] -> [2] -> 3
+    assert_eq!(Arg::Ind(1, 1).get_value(&m).unwrap(), 3f64);
+    // [[[1]]] -> [[2]] -> [3] -> 7
+    assert_eq!(Arg::Ind(1, 2).get_value(&m).unwrap(), 7f64);
+    m.var_add(1);
+    // [$A] -> [2] -> 3
+    assert_eq!(Arg::VarInd(0, 1).get_value(&m).unwrap(), 3f64);
+}
+
+#[test]
+fn read_value_invalid_memory_access(){
+    let t = Arg::Idx(-1);
+    let m = Mem::new();
+    assert_matches!(t.get_value(&m), Err(Error::InvalidMemAccess(-1)));
+}
+
+#[test]
+fn read_value_wrong_type(){
+    let t = Arg::Lbl(0);
+    let m = Mem::new();
+    let got = t.get_value(&m).unwrap_err();
+    assert_matches!(got, Error::WrongArgType(_, Tok::SYM_STR));
+}
+
+#[test]
+fn get_loc(){
+    let t = Arg::Idx(100);
+    let mut m = Mem::new();
+    m.pmem_allc(&[0.0; 100]);
+    assert_eq!(t.get_loc(&m).unwrap(), 100);
+    let t = Arg::Var(0);
+    m.var_add(100);
+    assert_eq!(t.get_loc(&m).unwrap(), 100);
+    m.mem_set(100, 30.0).unwrap();
+    let t = Arg::VarInd(0, 1);
+    assert_eq!(t.get_loc(&m).unwrap(), 30);
+    let t = Arg::Ind(100, 1);
+    assert_eq!(t.get_loc(&m).unwrap(), 30);
+    let t = Arg::Ltl(m.ltl_intern("asda"));
+    assert_eq!(t.get_loc(&m).unwrap(), -1);
+}
+
+#[test]
+fn get_loc_wrong_type(){
+    let t = Arg::Num(1.0);
+    let m = Mem::new();
+    let got = t.get_loc(&m);
+    assert_matches!(got, Err(Error::WrongArgType(_, Tok::NUM_STR)));
+}
+
+#[test]
+fn compile_interns_ltl(){
+    let mut m = Mem::new();
+    let mut c = Code::new();
+    for line in &["write: 1, \"asd\", 3", "write: 1, \"qwe\", 3", "write: 1, \"asd\", 3"] {
+        c.push(tokenize(line).unwrap(), LineSrc::default());
+    }
+    let nmem_len = m.nmem_len();
+    let sig: crate::op::Sig = &[ArgKind::Value, ArgKind::Ptr, ArgKind::Value];
+    c.compile(&mut m, &[sig]).unwrap();
+    // two distinct literals, each with 2 terminating slots
+    assert_eq!(m.nmem_len(), nmem_len + 2*(3+2));
+    let first = c.args(&c.inst_at(0).unwrap())[1];
+    let second = c.args(&c.inst_at(1).unwrap())[1];
+    let third = c.args(&c.inst_at(2).unwrap())[1];
+    assert_eq!(first, Arg::Ltl(-1));
+    assert_eq!(first, third);
+    assert_ne!(first, second);
+    assert_eq!(m.read_ltl(-1).unwrap(), "asd");
+}
+
+#[test]
+fn compile(){
+    let mut m = Mem::new();
+    let mut c = Code::new();
+    let mut t = tokenize("jc: [[$a]], L").unwrap();
+    if let Tok::Sym(ref mut hi) = t[0] {
+        hi.idx = 0;
+    }
+    c.push(t, LineSrc::default());
+    let sig: crate::op::Sig = &[ArgKind::Value, ArgKind::Lbl];
+    c.compile(&mut m, &[sig]).unwrap();
+    let inst = c.inst_at(0).unwrap();
+    assert_eq!(inst.op, 0);
+    assert_eq!(c.args(&inst), &[Arg::VarInd(0, 2), Arg::Lbl(0)]);
+}
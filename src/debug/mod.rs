@@ -0,0 +1,311 @@
+use std::io::{BufRead, Write};
+use std::fmt::Write as _;
+use crate::interp::{Interpreter, State};
+use crate::error::Error;
+
+const HELP: &str = "\
+break <line> | <file>:<line> | <label>   set breakpoint
+delete <n>                               delete breakpoint n
+info                                     list breakpoints and watches
+step [n]                                 execute n lines, 1 by default
+continue                                 run until breakpoint, watch or end
+print [n] | $var | [a..b]                print slot, var or slots a until b
+line                                     show next line to be executed
+labels                                   list labels
+watch [n] | $var                         stop when slot changes
+quit                                     stop debugging
+";
+
+enum Break{
+    Line(String, usize),  // file, line starting from 1
+    Label(String),
+}
+
+struct Watch{
+    idx: isize,
+    val: Option<f64>,  // None if slot is not allocated yet
+}
+
+// Drives Interpreter one line at a time with commands read from input
+pub struct Debugger{
+    bps: Vec<Break>,
+    watches: Vec<Watch>,
+    exit: Option<i32>,  // exit code once script is finished
+}
+
+impl Default for Debugger{
+    fn default() -> Self {
+        Debugger::new()
+    }
+}
+
+impl Debugger{
+    pub fn new() -> Debugger{
+        Debugger {
+            bps: Vec::new(),
+            watches: Vec::new(),
+            exit: None,
+        }
+    }
+
+    // Read commands until quit or end of input.
+    // Returns exit code of script, 0 if it is not finished
+    pub fn run(
+        &mut self,
+        i: &mut Interpreter,
+        input: &mut dyn BufRead,
+        output: &mut dyn Write
+    ) -> Result<i32, Error>
+    {
+        let mut out = String::new();
+        self.show_line(i, &mut out);
+        loop {
+            out.push_str("(lli) ");
+            if let Err(e) = output.write_all(out.as_bytes()).and_then(|_| output.flush()) {
+                return Err(Error::IoError(e));
+            }
+            out.clear();
+            let mut cmd = String::new();
+            match input.read_line(&mut cmd) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(e) => return Err(Error::IoError(e)),
+            }
+            if !self.command(i, &cmd, &mut out) {
+                break;
+            }
+        }
+        if let Err(e) = output.write_all(out.as_bytes()) {
+            return Err(Error::IoError(e));
+        }
+        Ok(self.exit.unwrap_or(0))
+    }
+
+    // Execute one command, appending its output to out.
+    // Returns false on quit
+    pub fn command(&mut self, i: &mut Interpreter, cmd: &str, out: &mut String) -> bool{
+        let mut words = cmd.split_whitespace();
+        let name = match words.next() {
+            Some(w) => w,
+            None => return true,
+        };
+        let arg = words.next();
+        match (name, arg) {
+            ("b", Some(a)) | ("break", Some(a)) => self.add_break(i, a, out),
+            ("d", Some(a)) | ("delete", Some(a)) => match a.parse::<usize>() {
+                Ok(n) if n < self.bps.len() => {
+                    self.bps.remove(n);
+                },
+                _ => out.push_str("No such breakpoint\n"),
+            },
+            ("info", None) => self.info(out),
+            ("s", n) | ("step", n) => {
+                let n = match n.map(|n| n.parse::<usize>()) {
+                    None => 1,
+                    Some(Ok(n)) => n,
+                    Some(Err(_)) => {
+                        out.push_str("step expects an unsigned integer\n");
+                        return true;
+                    },
+                };
+                for _ in 0..n {
+                    if !self.step(i, out) {
+                        break;
+                    }
+                }
+                self.show_line(i, out);
+            },
+            ("c", None) | ("continue", None) => {
+                while self.step(i, out) {
+                    if let Some(n) = self.at_break(i) {
+                        let _ = writeln!(out, "Breakpoint {}", n);
+                        break;
+                    }
+                }
+                self.show_line(i, out);
+            },
+            ("p", Some(a)) | ("print", Some(a)) => self.print(i, a, out),
+            ("l", None) | ("line", None) => self.show_line(i, out),
+            ("labels", None) => self.labels(i, out),
+            ("w", Some(a)) | ("watch", Some(a)) => match slot(i, a) {
+                Ok(idx) => self.watches.push(Watch {
+                    idx,
+                    val: i.mem_at(idx).ok(),
+                }),
+                Err(msg) => out.push_str(&msg),
+            },
+            ("q", None) | ("quit", None) => return false,
+            ("h", None) | ("help", None) => out.push_str(HELP),
+            _ => out.push_str("Unknown command, try help\n"),
+        }
+        true
+    }
+
+    // Execute one line and check watches.
+    // Returns false if script is finished or a watched slot changed
+    fn step(&mut self, i: &mut Interpreter, out: &mut String) -> bool{
+        if let Some(code) = self.exit {
+            let _ = writeln!(out, "Script is finished with exit code {}", code);
+            return false;
+        }
+        let mut r = i.step();
+        // report end of script right after its last line
+        if let (Ok(State::Running), None) = (&r, i.code()) {
+            r = i.step();
+        }
+        match r {
+            Ok(State::Running) => (),
+            Ok(State::Finished) => self.exit = Some(0),
+            Ok(State::Exited(code)) => self.exit = Some(code),
+            Err(e) => {
+                e.print(1);
+                self.exit = Some(1);
+            },
+        }
+        let mut changed = false;
+        for w in &mut self.watches {
+            let val = i.mem_at(w.idx).ok();
+            if val != w.val {
+                let _ = writeln!(out, "[{}]: {} -> {}", w.idx, fmt_val(w.val), fmt_val(val));
+                w.val = val;
+                changed = true;
+            }
+        }
+        if let Some(code) = self.exit {
+            let _ = writeln!(out, "Script is finished with exit code {}", code);
+            return false;
+        }
+        !changed
+    }
+
+    // index of breakpoint at the line to be executed
+    fn at_break(&self, i: &Interpreter) -> Option<usize>{
+        let code = i.code()?;
+        let span = code.span(code.ptr(), 0);
+        self.bps.iter().position(|b| match b {
+            Break::Line(file, line) =>
+                *file == span.file && *line == span.line,
+            Break::Label(name) => i.mem().label_hash.get(name)
+                .and_then(|l| i.mem().label_find(*l).ok())
+                == Some(code.ptr()),
+        })
+    }
+
+    fn add_break(&mut self, i: &Interpreter, a: &str, out: &mut String){
+        let b = if let Some((file, line)) = a.rsplit_once(':') {
+            match line.parse::<usize>() {
+                Ok(line) => Break::Line(file.to_owned(), line),
+                Err(_) => {
+                    out.push_str("Expects <file>:<line>\n");
+                    return;
+                },
+            }
+        }else if let Ok(line) = a.parse::<usize>() {
+            // line in the file being executed
+            let file = i.code().map_or("", |c| c.file()).to_owned();
+            Break::Line(file, line)
+        }else if i.mem().label_hash.contains_key(a) {
+            Break::Label(a.to_owned())
+        }else{
+            let _ = writeln!(out, "Unknown label: {}", a);
+            return;
+        };
+        self.bps.push(b);
+        let _ = writeln!(out, "Breakpoint {} set", self.bps.len()-1);
+    }
+
+    fn info(&self, out: &mut String){
+        for (n, b) in self.bps.iter().enumerate() {
+            let _ = match b {
+                Break::Line(file, line) => writeln!(out, "Breakpoint {}: {}:{}", n, file, line),
+                Break::Label(name) => writeln!(out, "Breakpoint {}: {}", n, name),
+            };
+        }
+        for w in &self.watches {
+            let _ = writeln!(out, "Watch [{}] = {}", w.idx, fmt_val(w.val));
+        }
+    }
+
+    fn show_line(&self, i: &Interpreter, out: &mut String){
+        match i.code() {
+            Some(c) => {
+                let s = c.span(c.ptr(), 0);
+                let _ = writeln!(out, "{}:{}: {}", s.file, s.line, s.text);
+            },
+            None => out.push_str("Script is finished\n"),
+        }
+    }
+
+    fn print(&self, i: &Interpreter, a: &str, out: &mut String){
+        // range of slots: [a..b]
+        if let Some((from, to)) = a.strip_prefix('[')
+            .and_then(|a| a.strip_suffix(']'))
+            .and_then(|a| a.split_once(".."))
+        {
+            let (from, to) = match (from.parse::<isize>(), to.parse::<isize>()) {
+                (Ok(f), Ok(t)) if (f < 0) == (t < 0) => (f, t),
+                _ => {
+                    out.push_str("Expects [a..b] within either pmem or nmem\n");
+                    return;
+                },
+            };
+            let step = if from <= to { 1 } else { -1 };
+            let mut idx = from;
+            while idx != to {
+                let _ = writeln!(out, "[{}] = {}", idx, fmt_val(i.mem_at(idx).ok()));
+                idx += step;
+            }
+            return;
+        }
+        match slot(i, a) {
+            Ok(idx) => {
+                if a.starts_with('$') {
+                    out.push_str(a);
+                    out.push_str(" = ");
+                }
+                let _ = writeln!(out, "[{}] = {}", idx, fmt_val(i.mem_at(idx).ok()));
+            },
+            Err(msg) => out.push_str(&msg),
+        }
+    }
+
+    fn labels(&self, i: &Interpreter, out: &mut String){
+        let m = i.mem();
+        let mut labels: Vec<(&String, usize)> = m.label_hash.iter()
+            .filter_map(|(name, l)| m.label_find(*l).ok().map(|l| (name, l)))
+            .collect();
+        labels.sort_by_key(|(_, l)| *l);
+        for (name, l) in labels {
+            // label refers to a line of the file being executed
+            match i.code().or_else(|| i.main_code()) {
+                Some(c) if l < c.len() =>
+                    { let _ = writeln!(out, "{}: line {}", name, c.span(l, 0).line); },
+                _ => { let _ = writeln!(out, "{}: end", name); },
+            }
+        }
+    }
+}
+
+// slot referred by [n] or $var
+fn slot(i: &Interpreter, a: &str) -> Result<isize, String>{
+    if let Some(name) = a.strip_prefix('$') {
+        return match i.mem().var_hash.get(name) {
+            Some(v) => i.mem().var_find(*v).map_err(|_| format!("Undefined variable: {}\n", name)),
+            None => Err(format!("Undefined variable: {}\n", name)),
+        };
+    }
+    match a.strip_prefix('[').and_then(|a| a.strip_suffix(']')).map(|a| a.parse::<isize>()) {
+        Some(Ok(idx)) => Ok(idx),
+        _ => Err("Expects [n] or $var\n".to_owned()),
+    }
+}
+
+fn fmt_val(v: Option<f64>) -> String{
+    match v {
+        Some(v) => v.to_string(),
+        None => "unallocated".to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,91 @@
+use super::*;
+
+fn setup(text: &str) -> Interpreter{
+    let mut i = Interpreter::new();
+    i.mem_mut().pmem_allc(&[0.0; 4]);
+    i.load_str("test", text).unwrap();
+    i
+}
+
+#[test]
+fn step_and_print(){
+    let mut i = setup("mov: [1], 5\nadd: [1], 1\nmov: [2], [0]");
+    let mut d = Debugger::new();
+    let mut out = String::new();
+    assert!(d.command(&mut i, "step", &mut out));
+    assert!(out.ends_with("test:2: add: [1], 1\n"));
+    out.clear();
+    d.command(&mut i, "print [1]", &mut out);
+    assert_eq!(out, "[1] = 5\n");
+    out.clear();
+    d.command(&mut i, "step 5", &mut out);
+    assert!(out.contains("exit code 0"));
+    out.clear();
+    d.command(&mut i, "p [0..3]", &mut out);
+    assert_eq!(out, "[0] = 6\n[1] = 5\n[2] = 6\n");
+    assert!(!d.command(&mut i, "quit", &mut out));
+}
+
+#[test]
+fn break_line_and_label(){
+    let mut i = setup("nop\nlbl: L\nnop\nnop");
+    let mut d = Debugger::new();
+    let mut out = String::new();
+    d.command(&mut i, "break 4", &mut out);
+    d.command(&mut i, "break L", &mut out);
+    assert_eq!(out, "Breakpoint 0 set\nBreakpoint 1 set\n");
+    out.clear();
+    d.command(&mut i, "continue", &mut out);
+    assert_eq!(out, "Breakpoint 1\ntest:3: nop\n");
+    out.clear();
+    d.command(&mut i, "c", &mut out);
+    assert_eq!(out, "Breakpoint 0\ntest:4: nop\n");
+    out.clear();
+    d.command(&mut i, "labels", &mut out);
+    assert_eq!(out, "L: line 3\n");
+}
+
+#[test]
+fn watch(){
+    let mut i = setup("var: a, [2]\nnop\nmov: $a, 3\nnop");
+    let mut d = Debugger::new();
+    let mut out = String::new();
+    d.command(&mut i, "step", &mut out);
+    d.command(&mut i, "watch $a", &mut out);
+    out.clear();
+    d.command(&mut i, "continue", &mut out);
+    assert_eq!(out, "[2]: 0 -> 3\ntest:4: nop\n");
+    out.clear();
+    d.command(&mut i, "print $a", &mut out);
+    assert_eq!(out, "$a = [2] = 3\n");
+}
+
+#[test]
+fn break_in_src(){
+    let path = std::env::temp_dir().join("lli_debug_test_src.lli");
+    std::fs::write(&path, "nop\nmov: [1], 2\n").unwrap();
+    let mut i = setup(&format!("src: {}\nnop", path.display()));
+    let mut d = Debugger::new();
+    let mut out = String::new();
+    d.command(&mut i, &format!("break {}:2", path.display()), &mut out);
+    out.clear();
+    d.command(&mut i, "continue", &mut out);
+    assert_eq!(out, format!("Breakpoint 0\n{}:2: mov: [1], 2\n", path.display()));
+    out.clear();
+    d.command(&mut i, "step", &mut out);
+    // back in the sourcing file
+    assert_eq!(out, "test:2: nop\n");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn run_commands(){
+    let mut i = setup("mov: [1], 7\nexit: 3");
+    let mut d = Debugger::new();
+    let mut input: &[u8] = b"step\nprint [1]\ncontinue\n";
+    let mut output = Vec::new();
+    assert_eq!(d.run(&mut i, &mut input, &mut output).unwrap(), 3);
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.contains("[1] = 7\n"));
+    assert!(output.contains("exit code 3"));
+}
@@ -1,5 +1,34 @@
+// Where an error happened
+#[derive(Clone, PartialEq, Debug)]
+pub struct Span{
+    pub file: String,
+    pub line: usize,  // starts from 1
+    pub col: usize,  // byte offset in text
+    pub text: String,  // source line
+}
+
+impl Span{
+    fn print(&self) {
+        let lineno = self.line.to_string();
+        let pad = " ".repeat(lineno.len());
+        // keep tabs so that caret lines up with the source
+        let caret: String = self.text.get(..self.col).unwrap_or("")
+            .chars()
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        eprintln!("{} |", pad);
+        eprintln!("{} | {}", lineno, self.text);
+        eprintln!("{} | {}^", pad, caret);
+    }
+}
+
 #[derive(Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Error{
+    // error with location attached
+    At(Box<Error>, Span),
+    // error about operand of an op, index in args. Turned into At by interpreter
+    Arg(usize, Box<Error>),
 
     // lexing
     WrongTokTypeForOp(&'static str),  // got
@@ -20,6 +49,7 @@ pub enum Error{
     UndefinedVar(String),  // var_name
     UnknownOp(String),  // op_name
     UnknownLabel(String),  // label_name
+    DuplicateOp(String),  // op_name
     
     // runtime 
     InvalidMemAccess(isize),  // idx
@@ -31,21 +61,132 @@ pub enum Error{
     BadFileDescriptor(std::os::unix::io::RawFd),  // fd tried to open
     IoError(std::io::Error),  // returned from std::io functions
     InvalidOpenOption(u64),  // o_val
+    CallStackOverflow(usize),  // max depth
+    CallStackUnderflow,
+    // strict numeric mode
+    DivByZero,
+    IntegerOverflow(f64),  // result
+    NanResult,
+    // printf
+    InvalidFormat(String),  // reason
+    // read_num
+    Eof,
+    // heap
+    DoubleFree(isize),  // idx
+    NotHeapPtr(isize),  // idx
+    // limits
+    PmemLimit(usize),  // max slots
+    NmemLimit(usize),  // max slots
+    FdLimit(usize),  // max open fds
+    InstLimit(u64),  // max executed lines
+    Timeout(std::time::Duration),  // limit
+    // sandbox
+    PermissionDenied(String),  // path
 }
 
 impl Error {
+    // Attach location to error.
+    // Keeps the innermost location if there is one already
+    pub fn at(self, span: Span) -> Error {
+        match self {
+            Error::At(..) => self,
+            _ => Error::At(Box::new(self), span),
+        }
+    }
+
+    // Mark error as caused by operand pos of an op.
+    // Keeps the innermost operand if there is one already
+    pub fn arg(self, pos: usize) -> Error {
+        match self {
+            Error::At(..) | Error::Arg(..) => self,
+            _ => Error::Arg(pos, Box::new(self)),
+        }
+    }
+
+    // Code of error visible to scripts.
+    // errno for errors from the os, negative for lli errors
+    pub fn code(&self) -> i32 {
+        match self {
+            Error::At(e, _) | Error::Arg(_, e) => e.code(),
+            Error::WrongTokTypeForOp(_) => -1,
+            Error::ParseNumError(_) => -2,
+            Error::ParseIdxError(_) => -3,
+            Error::UnterminatedIdx => -4,
+            Error::EmptyIdx => -5,
+            Error::MissingVarName => -6,
+            Error::EmptyToken => -7,
+            Error::UnexpectedChar(_) => -8,
+            Error::NonDelimAfterSymEnd(_) => -9,
+            Error::DoubleQuoteInMiddle => -10,
+            Error::UnknownEscapeSequence(_) => -11,
+            Error::UndefinedVar(_) => -12,
+            Error::UnknownOp(_) => -13,
+            Error::UnknownLabel(_) => -14,
+            Error::DuplicateOp(_) => -15,
+            Error::InvalidMemAccess(_) => -16,
+            Error::WriteToNMem(_) => -17,
+            Error::WrongArgCount(..) => -18,
+            Error::WrongArgType(..) => -19,
+            Error::NegativeOrNotInterger(_) => -20,
+            Error::NotInterger(_) => -21,
+            Error::BadFileDescriptor(_) => -22,
+            Error::IoError(e) => e.raw_os_error().unwrap_or(-23),
+            Error::InvalidOpenOption(_) => -24,
+            Error::CallStackOverflow(_) => -25,
+            Error::CallStackUnderflow => -26,
+            Error::DivByZero => -27,
+            Error::IntegerOverflow(_) => -28,
+            Error::NanResult => -29,
+            Error::InvalidFormat(_) => -30,
+            Error::Eof => -31,
+            Error::DoubleFree(_) => -32,
+            Error::NotHeapPtr(_) => -33,
+            Error::PmemLimit(_) => -34,
+            Error::NmemLimit(_) => -35,
+            Error::FdLimit(_) => -36,
+            Error::InstLimit(_) => -37,
+            Error::Timeout(_) => -38,
+            Error::PermissionDenied(_) => -39,
+        }
+    }
+
+    // Value that caused the error, 0 if there is none
+    pub fn payload(&self) -> f64 {
+        match self {
+            Error::At(e, _) | Error::Arg(_, e) => e.payload(),
+            Error::InvalidMemAccess(idx) | Error::WriteToNMem(idx) |
+            Error::DoubleFree(idx) | Error::NotHeapPtr(idx) => *idx as f64,
+            Error::WrongArgCount(_, got) => *got as f64,
+            Error::NegativeOrNotInterger(got) | Error::NotInterger(got) |
+            Error::IntegerOverflow(got) => *got,
+            Error::BadFileDescriptor(fd) => *fd as f64,
+            Error::InvalidOpenOption(o) => *o as f64,
+            Error::CallStackOverflow(depth) | Error::PmemLimit(depth) |
+            Error::NmemLimit(depth) | Error::FdLimit(depth) => *depth as f64,
+            Error::InstLimit(limit) => *limit as f64,
+            Error::Timeout(limit) => limit.as_secs_f64(),
+            _ => 0.0,
+        }
+    }
+
     // level 0 to silence error msg
     pub fn print(&self, level: usize) {
         if level == 0 {
             return;
         }
         match self {
+            Error::At(e, s) => {
+                eprint!("{}:{}:{}: ", s.file, s.line, s.col+1);
+                e.print(level);
+                s.print();
+            },
+            Error::Arg(_, e) => e.print(level),
             Error::WrongTokTypeForOp(got) =>
                 eprintln!("Expects Sym, got: {}", got),
             Error::ParseNumError(e) =>
-                eprintln!("{}", e.to_string()),
+                eprintln!("{}", e),
             Error::ParseIdxError(e) =>
-                eprintln!("{}", e.to_string()),
+                eprintln!("{}", e),
             Error::UnterminatedIdx =>
                 eprintln!("Unterminated Idx"),
             Error::EmptyIdx =>
@@ -68,6 +209,8 @@ impl Error {
                 eprintln!("Undefined variable: {}", var_name),
             Error::UnknownOp(op_name) => 
                 eprintln!("Unkwon op: {}", op_name),
+            Error::DuplicateOp(op_name) => 
+                eprintln!("Op already exists: {}", op_name),
             Error::UnknownLabel(label_name) => 
                 eprintln!("Unkwon label: {}", label_name),
 
@@ -92,9 +235,50 @@ impl Error {
             Error::BadFileDescriptor(fd) =>
                 eprintln!("Bad file descriptor: {}", fd),
             Error::IoError(e) =>
-                eprintln!("IO error: {}", e.to_string()),
+                eprintln!("IO error: {}", e),
             Error::InvalidOpenOption(o) =>
                 eprintln!("Invalid open option: {}", o),
+            Error::CallStackOverflow(depth) =>
+                eprintln!("Call stack overflow, max depth: {}", depth),
+            Error::CallStackUnderflow =>
+                eprintln!("Return without call"),
+            Error::DivByZero =>
+                eprintln!("Division by zero"),
+            Error::IntegerOverflow(got) =>
+                eprintln!("Integer result out of exact range: {}", got),
+            Error::NanResult =>
+                eprintln!("Result is NaN"),
+            Error::InvalidFormat(reason) =>
+                eprintln!("Invalid format: {}", reason),
+            Error::Eof =>
+                eprintln!("Unexpected end of input"),
+            Error::DoubleFree(idx) =>
+                eprintln!("Block is already freed: {}", idx),
+            Error::NotHeapPtr(idx) =>
+                eprintln!("Not a heap block: {}", idx),
+            Error::PmemLimit(max) =>
+                eprintln!("pmem limit exceeded, max slots: {}", max),
+            Error::NmemLimit(max) =>
+                eprintln!("nmem limit exceeded, max slots: {}", max),
+            Error::FdLimit(max) =>
+                eprintln!("Too many open files, max: {}", max),
+            Error::InstLimit(max) =>
+                eprintln!("Instruction limit exceeded, max: {}", max),
+            Error::Timeout(limit) =>
+                eprintln!("Timed out after {:?}", limit),
+            Error::PermissionDenied(path) =>
+                eprintln!("Permission denied by sandbox: {}", path),
         }
     }
 }
+
+// Tag error of reading or writing an operand with its index
+pub trait ArgErr<T> {
+    fn arg(self, pos: usize) -> Result<T, Error>;
+}
+
+impl<T> ArgErr<T> for Result<T, Error> {
+    fn arg(self, pos: usize) -> Result<T, Error> {
+        self.map_err(|e| e.arg(pos))
+    }
+}
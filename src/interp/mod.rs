@@ -0,0 +1,521 @@
+use num_traits::FromPrimitive;
+use ahash::AHashMap;
+use std::time::{Duration, Instant};
+use crate::mem::Mem;
+use crate::code::{Code, LineSrc, Arg};
+use crate::lex::{self, Tok};
+use crate::error::Error;
+use crate::op::{self, Signal};
+
+fn assign_opcode(
+    op_idx_table: &AHashMap<String, usize>, 
+    t: &mut [Tok]
+) -> Result<usize, Error> 
+{
+    if let Tok::Sym(ref mut n) = t[0] {
+        // lookup and assign opcode
+        let s: &str = &n.sym;
+        n.idx = match op_idx_table.get(s) {
+            Some(i) => *i,
+            None => return Err(Error::UnknownOp(s.to_string())),
+        };
+        Ok(n.idx)
+    }else{
+        Err(Error::WrongTokTypeForOp(t[0].to_type_str()))
+    }
+}
+
+fn create_symbol_table(
+    opcode: usize,
+    sig: op::Sig,
+    m: &mut Mem, 
+    c: &mut Code, 
+    t: &mut [Tok]
+) -> Result<(), Error>
+{
+    match FromPrimitive::from_usize(opcode) {
+        Some(op::Opcode::Lbl) | Some(op::Opcode::Als) => if let Tok::Sym(ref mut hi) = t[1] {
+            hi.idx = match m.label_hash.get(&hi.sym) {
+                Some(i) => *i,
+                None => {
+                    let idx = m.label_add(c.len()+1);
+                    m.label_hash.insert(hi.sym.to_owned(), idx);
+                    idx
+                },
+            };
+        },
+        _ => (),
+    };
+    // var names are known from op signature, registered ops may take them too
+    for (pos, k) in sig.iter().enumerate() {
+        if *k != op::ArgKind::VarName {
+            continue;
+        }
+        if let Tok::Sym(ref mut hi) = t[pos+1] {
+            hi.idx = match m.var_hash.get(&hi.sym) {
+                Some(i) => *i,
+                None => {
+                    let idx = m.var_add(0);
+                    m.var_hash.insert(hi.sym.to_owned(), idx);
+                    idx
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn replace_lbl(tok: &mut Tok, m: &Mem)  -> Result<(), Error>{
+    if let Tok::Sym(ref mut hi) = tok {
+        hi.idx = match m.label_hash.get(&hi.sym) {
+            Some(i) => *i,
+            None =>
+                return Err(Error::UnknownLabel(hi.sym.clone())),
+        }
+    }
+    Ok(())
+}
+
+// replace symbols in a line.
+// Error comes with index of the offending token
+fn replace_line_sym(m: &Mem, op_sig: &[op::Sig], line: &mut [Tok]) -> Result<(), (Error, usize)> {
+    if let Tok::Sym(ref hi) = line[0] {
+        // labels are known from op signature
+        for (pos, k) in op_sig[hi.idx].iter().enumerate() {
+            if *k == op::ArgKind::Lbl {
+                replace_lbl(&mut line[pos+1], m).map_err(|e| (e, pos+1))?;
+            }
+        }
+        for (pos, a) in line.iter_mut().enumerate().skip(1) {
+            // Var or VarIdx
+            if let Tok::Var(ref mut hi) = a {
+                hi.idx = match m.var_hash.get(&hi.sym) {
+                    Some(i) => *i,
+                    None => 
+                        return Err((Error::UndefinedVar(hi.sym.to_owned()), pos)),
+                };
+                continue;
+            }else if let Tok::Idx(ref mut i) = a {
+                let mut idx = i;
+                while let lex::Idx::Idx(b) = idx {
+                    idx = b;
+                }
+                if let lex::Idx::Var(v) = idx {
+                    v.idx = match m.var_hash.get(&v.sym) {
+                        Some(c) => *c,
+                        None =>
+                            return Err((Error::UndefinedVar(v.sym.to_owned()), pos)),
+                    }
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// loop through all lines to replace symbols
+fn replace_sym(m: &Mem, op_sig: &[op::Sig], c: &mut Code) -> Result<(), Error> {
+    for i in 0..c.len() {
+        if let Err((e, pos)) = replace_line_sym(m, op_sig, c.at_mut(i).unwrap()) {
+            return Err(e.at(c.span(i, pos)));
+        }
+    }
+    Ok(())
+}
+
+pub(crate) fn preprocess(
+    op_idx_table: &AHashMap<String, usize>, 
+    op_sig: &[op::Sig],
+    m: &mut Mem, 
+    c: &mut Code,
+    mut t: Vec<Tok>,
+    src: LineSrc
+) -> Result<(), Error> {
+    // skip empty lines
+    if t.is_empty() {
+        return Ok(());
+    }
+    let opcode = assign_opcode(op_idx_table, &mut t)
+        .map_err(|e| e.at(src.span(c.file(), 0)))?;
+    // check argc and arg types
+    op::check_args(op_sig[opcode], &t[1..])
+        .map_err(|(e, i)| match e {
+            // point at op if argc is wrong
+            Error::WrongArgCount(..) => e.at(src.span(c.file(), 0)),
+            _ => e.at(src.span(c.file(), i+1)),
+        })?;
+    // create symbol table
+    create_symbol_table(opcode, op_sig[opcode], m, c, &mut t)
+        .map_err(|e| e.at(src.span(c.file(), 1)))?;
+    c.push(t, src);
+    Ok(())
+}
+
+// Tokenize, preprocess and compile text, appending to c.
+// Line numbers of text start after first
+fn parse_text(
+    op_idx_table: &AHashMap<String, usize>,
+    op_sig: &[op::Sig],
+    m: &mut Mem,
+    c: &mut Code,
+    first: usize,
+    text: &str
+) -> Result<(), Error> {
+    for (i, s) in text.lines().enumerate() {
+        let mut src = LineSrc {
+            line: first+i+1,
+            text: s.to_owned(),
+            cols: Vec::new(),
+        };
+        let t = match lex::tokenize_cols(&src.text) {
+            Ok((t, cols)) => {
+                src.cols = cols;
+                t
+            },
+            Err((e, col)) => {
+                src.cols.push(col);
+                return Err(e.at(src.span(c.file(), 0)));
+            },
+        };
+        // preprocess and push t to code
+        preprocess(op_idx_table, op_sig, m, c, t, src)?;
+    }
+    replace_sym(m, op_sig, c)?;
+    c.compile(m, op_sig)
+}
+
+// Write error info to [0] to [3] and jump to the label registered by trap op,
+// in the file that registered it. Files sourced after that are dropped.
+// file and line are where the error happened.
+// Returns the original error if the file of the handler is already finished
+fn trap_error(
+    m: &mut Mem,
+    code: &mut Vec<Code>,
+    trap: (usize, usize),
+    e: Error,
+    file: &str,
+    line: usize
+) -> Result<Signal, Error> {
+    let (label, depth) = trap;
+    if depth == 0 || depth > code.len() {
+        return Err(e);
+    }
+    let loc = match m.label_find(label) {
+        Ok(l) => l,
+        Err(_) => return Err(e),
+    };
+    // file name can be printed with print_str
+    let file = m.ltl_intern(file) as f64;
+    for (i, v) in [e.payload(), e.code() as f64, line as f64, file].iter().enumerate() {
+        if m.mem_set(i as isize, *v).is_err() {
+            return Err(e);
+        }
+    }
+    code.truncate(depth);
+    code[depth-1].ptr_set(loc);
+    Ok(Signal::Jmp(loc))
+}
+
+// State of interpreter after a step
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum State{
+    Running,
+    Finished,  // all lines are executed
+    Exited(i32),  // exit op is executed, with exit code
+}
+
+// file name of code appended by eval
+const EVAL_FILE: &str = "<eval>";
+
+// Owns memory, code and op table.
+// Files loaded by src are pushed on top of the code stack 
+// and popped after their last line is executed
+pub struct Interpreter{
+    mem: Mem,
+    code: Vec<Code>,
+    op_idx_table: AHashMap<String, usize>,
+    op_vec: Vec<op::OpFunc>,
+    op_sig: Vec<op::Sig>,
+    op_name: Vec<String>,
+    signal: Signal,  // returned by the last executed op
+    inst_limit: Option<u64>,  // max executed lines
+    timeout: Option<Duration>,  // max time since the first executed line
+    insts: u64,  // executed lines
+    started: Option<Instant>,
+}
+
+impl Default for Interpreter{
+    fn default() -> Self {
+        Interpreter::new()
+    }
+}
+
+impl Interpreter{
+    pub fn new() -> Interpreter{
+        let mut i = Interpreter {
+            mem: Mem::new(),
+            code: Vec::new(),
+            op_idx_table: AHashMap::new(),
+            op_vec: Vec::new(),
+            op_sig: Vec::new(),
+            op_name: Vec::new(),
+            signal: Signal::None,
+            inst_limit: None,
+            timeout: None,
+            insts: 0,
+            started: None,
+        };
+        op::init_op_table(&mut i.op_idx_table, &mut i.op_vec, &mut i.op_sig);
+        i.op_name = vec![String::new(); i.op_vec.len()];
+        for (name, op) in &i.op_idx_table {
+            i.op_name[*op] = name.clone();
+        }
+        i
+    }
+
+    // Register a custom op.
+    // Lines using it are checked against sig during preprocess like builtin ops
+    pub fn register_op<F>(&mut self, name: &str, sig: op::Sig, f: F) -> Result<(), Error>
+    where F: Fn(&[Arg], &mut Mem) -> Result<Signal, Error> + Send + 'static
+    {
+        match op::add_op(&mut self.op_idx_table, &mut self.op_vec, &mut self.op_sig, name, sig, f) {
+            Some(_) => {
+                self.op_name.push(name.to_owned());
+                Ok(())
+            },
+            None => Err(Error::DuplicateOp(name.to_owned())),
+        }
+    }
+
+    // Tokenize and preprocess text into a new Code.
+    // name is used in error messages
+    // Labels and vars of a text that fails are forgotten
+    fn parse(&mut self, name: &str, text: &str) -> Result<Code, Error>{
+        let mut code = Code::new();
+        code.set_file(name);
+        let mark = self.mem.mark();
+        if let Err(e) = parse_text(&self.op_idx_table, &self.op_sig, &mut self.mem, &mut code, 0, text) {
+            self.mem.rollback(mark);
+            return Err(e);
+        }
+        Ok(code)
+    }
+
+    fn read_file(&mut self, path: &str) -> Result<Code, Error>{
+        let text = match std::fs::read_to_string(path) {
+            Ok(t) => t,
+            Err(e) => return Err(Error::IoError(e)),
+        };
+        self.parse(path, &text)
+    }
+
+    // Load script from string, replacing loaded code.
+    // Memory, labels and vars are kept
+    pub fn load_str(&mut self, name: &str, text: &str) -> Result<(), Error>{
+        let code = self.parse(name, text)?;
+        self.code = vec![code];
+        Ok(())
+    }
+
+    // Load script from file, replacing loaded code.
+    // Memory, labels and vars are kept
+    pub fn load_file(&mut self, path: &str) -> Result<(), Error>{
+        let code = self.read_file(path)?;
+        self.code = vec![code];
+        Ok(())
+    }
+
+    // Append text to the code loaded by previous eval and execute it.
+    // Labels and vars defined earlier stay valid, 
+    // jumping back to them re-executes the lines after them
+    pub fn eval(&mut self, text: &str) -> Result<State, Error>{
+        if self.code.is_empty() {
+            let mut code = Code::new();
+            code.set_file(EVAL_FILE);
+            self.code.push(code);
+        }
+        // drop files left by previous eval
+        self.code.truncate(1);
+        let code = &mut self.code[0];
+        let first = code.len();
+        let mark = self.mem.mark();
+        if let Err(e) = parse_text(&self.op_idx_table, &self.op_sig, &mut self.mem, code, first, text) {
+            code.truncate(first);
+            self.mem.rollback(mark);
+            return Err(e);
+        }
+        self.reset_budget();
+        // stop before the last file is popped, so later eval can append to it
+        while self.code().is_some() {
+            match self.step() {
+                Ok(State::Running) => (),
+                Ok(s) => return Ok(s),
+                Err(e) => {
+                    // skip the rest, so the offending line is not executed again
+                    self.code.truncate(1);
+                    let code = &mut self.code[0];
+                    code.ptr_set(code.len());
+                    return Err(e);
+                },
+            }
+        }
+        Ok(State::Finished)
+    }
+
+    // Execute one line
+    pub fn step(&mut self) -> Result<State, Error>{
+        // pop finished files
+        while let Some(c) = self.code.last() {
+            if c.ptr() < c.len() {
+                break;
+            }
+            self.code.pop();
+        }
+        // budgets are checked before the line, errors can't be trapped
+        if !self.code.is_empty() {
+            if let Err(e) = self.budget() {
+                let c = &self.code[self.code.len()-1];
+                return Err(e.at(c.span(c.ptr(), 0)));
+            }
+        }
+        let code = match self.code.last_mut() {
+            Some(c) => c,
+            None => return Ok(State::Finished),
+        };
+        let r = match op::exec(&self.op_vec, &mut self.mem, code) {
+            Ok(s) => s.respond(&mut self.mem, code).map(|_| s),
+            Err(e) => Err(e),
+        };
+        self.signal = match r {
+            Ok(s) => s,
+            Err(e) => {
+                // ops tag errors about an operand with its index, others point at op
+                let (tok, e) = match e {
+                    Error::Arg(pos, e) => (pos+1, *e),
+                    e => (0, e),
+                };
+                let span = code.span(code.ptr(), tok);
+                let (file, line) = (span.file.clone(), span.line);
+                let e = e.at(span);
+                match self.mem.take_trap() {
+                    Some(trap) => trap_error(&mut self.mem, &mut self.code, trap, e, &file, line)?,
+                    None => return Err(e),
+                }
+            },
+        };
+        match self.signal {
+            Signal::Exit(exit_code) => 
+                return Ok(State::Exited(exit_code)),
+            Signal::Trap(label) => self.mem.set_trap(Some((label, self.code.len()))),
+            Signal::Src(ref name) => {
+                // src line is already stepped over, 
+                // resume after it when the sourced file finishes
+                // errors are about the file name
+                let code = &self.code[self.code.len()-1];
+                let span = code.span(code.ptr()-1, 1);
+                let name = name.clone();
+                let checked = match self.mem.sandbox() {
+                    Some(sandbox) => sandbox.check_src(&name),
+                    None => Ok(()),
+                };
+                match checked.and_then(|_| self.read_file(&name)) {
+                    Ok(src) => self.code.push(src),
+                    // handled by trap like op errors
+                    Err(e) => {
+                        let (file, line) = (span.file.clone(), span.line);
+                        let e = e.at(span);
+                        let trap = match self.mem.take_trap() {
+                            Some(trap) => trap,
+                            None => return Err(e),
+                        };
+                        self.signal = trap_error(&mut self.mem, &mut self.code, trap, e, &file, line)?;
+                    },
+                }
+            },
+            _ => (),
+        }
+        Ok(State::Running)
+    }
+
+    // Count executed line against instruction limit and timeout
+    fn budget(&mut self) -> Result<(), Error>{
+        self.insts += 1;
+        if let Some(limit) = self.inst_limit {
+            if self.insts > limit {
+                return Err(Error::InstLimit(limit));
+            }
+        }
+        if let Some(timeout) = self.timeout {
+            let started = *self.started.get_or_insert_with(Instant::now);
+            // clock is read once in a while, it costs more than a line
+            if self.insts % 1024 == 0 && started.elapsed() > timeout {
+                return Err(Error::Timeout(timeout));
+            }
+        }
+        Ok(())
+    }
+
+    // Stop with InstLimit once more than limit lines are executed
+    pub fn set_inst_limit(&mut self, limit: Option<u64>){
+        self.inst_limit = limit;
+    }
+
+    // Stop with Timeout once the time since the first executed line exceeds limit
+    pub fn set_timeout(&mut self, limit: Option<Duration>){
+        self.timeout = limit;
+    }
+
+    // Start counting lines and time for the limits again.
+    // Done by run and eval, callers of step do it before each script
+    pub fn reset_budget(&mut self){
+        self.insts = 0;
+        self.started = None;
+    }
+
+    // Run until all lines are executed or exit op is executed.
+    // Limits apply to each run separately.
+    // Returns exit code
+    pub fn run(&mut self) -> Result<i32, Error>{
+        self.reset_budget();
+        loop {
+            match self.step()? {
+                State::Running => (),
+                State::Finished => return Ok(0),
+                State::Exited(exit_code) => return Ok(exit_code),
+            }
+        }
+    }
+
+    pub fn mem(&self) -> &Mem{
+        &self.mem
+    }
+    pub fn mem_mut(&mut self) -> &mut Mem{
+        &mut self.mem
+    }
+    pub fn mem_at(&self, i: isize) -> Result<f64, Error>{
+        self.mem.mem_at(i)
+    }
+    pub fn mem_set(&mut self, i: isize, v: f64) -> Result<(), Error>{
+        self.mem.mem_set(i, v)
+    }
+
+    // code being executed, None if finished.
+    // Files whose lines are all executed are skipped
+    pub fn code(&self) -> Option<&Code>{
+        self.code.iter().rev().find(|c| c.ptr() < c.len())
+    }
+    pub fn op_name(&self, op: usize) -> Option<&str>{
+        self.op_name.get(op).map(|s| s.as_str())
+    }
+    // signal returned by the last executed op
+    pub fn signal(&self) -> &Signal{
+        &self.signal
+    }
+    // code loaded by load_str, load_file or eval
+    pub fn main_code(&self) -> Option<&Code>{
+        self.code.first()
+    }
+}
+
+#[cfg(test)]
+mod test;
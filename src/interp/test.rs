@@ -0,0 +1,342 @@
+use super::*;
+
+#[test]
+fn load_str_run(){
+    let mut i = Interpreter::new();
+    i.mem_mut().pmem_allc(&[0.0]);
+    i.load_str("test", "add: 1, 2\nmov: [1], [0]").unwrap();
+    assert_eq!(i.run().unwrap(), 0);
+    assert_eq!(i.mem_at(1).unwrap(), 3.0);
+}
+
+#[test]
+fn load_str_error(){
+    let mut i = Interpreter::new();
+    // labels and vars of the failed load are forgotten
+    assert_matches!(i.load_str("test", "var: A\nlbl: L\njmp: M"), Err(Error::At(..)));
+    assert!(i.mem().var_hash.is_empty());
+    assert!(i.mem().label_hash.is_empty());
+    assert_matches!(i.load_str("test", "lbl: M\njmp: L"), Err(Error::At(..)));
+    assert!(i.mem().label_hash.is_empty());
+}
+
+#[test]
+fn step(){
+    let mut i = Interpreter::new();
+    i.load_str("test", "add: 1, 2\nadd: [0], 2").unwrap();
+    assert_eq!(i.step().unwrap(), State::Running);
+    assert_eq!(i.mem_at(0).unwrap(), 3.0);
+    assert_eq!(i.step().unwrap(), State::Running);
+    assert_eq!(i.mem_at(0).unwrap(), 5.0);
+    assert_eq!(i.step().unwrap(), State::Finished);
+}
+
+#[test]
+fn exit(){
+    let mut i = Interpreter::new();
+    i.load_str("test", "exit: 3\nmov: [0], 1").unwrap();
+    assert_eq!(i.run().unwrap(), 3);
+    assert_eq!(i.mem_at(0).unwrap(), 0.0);
+}
+
+#[test]
+fn mem_set(){
+    let mut i = Interpreter::new();
+    i.mem_set(0, 4.0).unwrap();
+    i.load_str("test", "mul: [0], [0]").unwrap();
+    i.run().unwrap();
+    assert_eq!(i.mem_at(0).unwrap(), 16.0);
+}
+
+#[test]
+fn runtime_error(){
+    let mut i = Interpreter::new();
+    i.load_str("test", "nop\nmov: [5], 1").unwrap();
+    let e = i.run().unwrap_err();
+    if let Error::At(e, s) = e {
+        assert_matches!(*e, Error::InvalidMemAccess(_));
+        assert_eq!((s.file.as_str(), s.line), ("test", 2));
+    }else{
+        panic!("Error without location");
+    }
+}
+
+#[test]
+fn src(){
+    let path = std::env::temp_dir().join("lli_interp_test_src.lli");
+    std::fs::write(&path, "add: [0], 10\n").unwrap();
+    let mut i = Interpreter::new();
+    let text = format!("mov: [0], 1\nsrc: {}\nadd: [0], 100", path.display());
+    i.load_str("test", &text).unwrap();
+    i.step().unwrap();
+    i.step().unwrap();
+    // sourced file is on top of code stack
+    assert_eq!(i.code().unwrap().file(), path.to_str().unwrap());
+    assert_eq!(i.run().unwrap(), 0);
+    assert_eq!(i.mem_at(0).unwrap(), 111.0);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn register_op(){
+    let mut i = Interpreter::new();
+    i.register_op("sq", &[op::ArgKind::WPtr, op::ArgKind::Value], |v, m| {
+        let x = v[1].get_value(m)?;
+        v[0].write_value(m, x*x)?;
+        Ok(Signal::None)
+    }).unwrap();
+    i.mem_mut().pmem_allc(&[0.0]);
+    i.load_str("test", "sq: [1], 3\nsq: [1], [1]").unwrap();
+    i.run().unwrap();
+    assert_eq!(i.mem_at(1).unwrap(), 81.0);
+}
+
+#[test]
+fn register_op_lbl(){
+    let mut i = Interpreter::new();
+    // jump to label if value is zero
+    i.register_op("jz", &[op::ArgKind::Value, op::ArgKind::Lbl], |v, m| {
+        if v[0].get_value(m)? == 0.0 {
+            Ok(Signal::Jmp(m.label_find(v[1].get_lbl()?)?))
+        }else{
+            Ok(Signal::None)
+        }
+    }).unwrap();
+    i.load_str("test", "jz: 0, L\nmov: [0], 1\nlbl: L").unwrap();
+    i.run().unwrap();
+    assert_eq!(i.mem_at(0).unwrap(), 0.0);
+}
+
+#[test]
+fn register_op_var_name(){
+    let mut i = Interpreter::new();
+    // var pointing one past ptr
+    i.register_op("var_next", &[op::ArgKind::VarName, op::ArgKind::Ptr], |v, m| {
+        m.var_set(v[0].get_var()?, v[1].get_loc(m)?+1);
+        Ok(Signal::None)
+    }).unwrap();
+    i.mem_mut().pmem_allc(&[0.0]);
+    i.load_str("test", "var_next: n, [3]\nmov: $n, 5").unwrap();
+    i.run().unwrap();
+    assert_eq!(i.mem_at(4).unwrap(), 5.0);
+}
+
+#[test]
+fn register_op_checked(){
+    let mut i = Interpreter::new();
+    i.register_op("f", &[op::ArgKind::Value], |_, _| Ok(Signal::None)).unwrap();
+    assert_matches!(i.register_op("f", &[], |_, _| Ok(Signal::None)),
+        Err(Error::DuplicateOp(_)));
+    assert_matches!(i.register_op("add", &[], |_, _| Ok(Signal::None)),
+        Err(Error::DuplicateOp(_)));
+    let e = i.load_str("test", "f: 1, 2").unwrap_err();
+    assert_matches!(e, Error::At(ref e, _) if matches!(**e, Error::WrongArgCount(1, 2)));
+    let e = i.load_str("test", "f: \"asd\"").unwrap_err();
+    assert_matches!(e, Error::At(ref e, _) if matches!(**e, Error::WrongArgType(..)));
+}
+
+#[test]
+fn eval(){
+    let mut i = Interpreter::new();
+    assert_eq!(i.eval("add: 1, 2").unwrap(), State::Finished);
+    assert_eq!(i.mem_at(0).unwrap(), 3.0);
+    i.eval("lbl: L").unwrap();
+    assert_eq!(i.eval("add: [0], 1").unwrap(), State::Finished);
+    assert_eq!(i.mem_at(0).unwrap(), 4.0);
+    // unknown label is rejected without keeping the line
+    assert_matches!(i.eval("jmp: M"), Err(Error::At(..)));
+    assert_eq!(i.code[0].len(), 3);
+    i.eval("add: [0], 1").unwrap();
+    assert_eq!(i.mem_at(0).unwrap(), 5.0);
+    // literal on the last line fails while compiling, earlier lines are dropped too
+    let nmem = i.mem_mut().nmem_len();
+    i.mem_mut().set_nmem_limit(nmem);
+    let text = "var: A\nlbl: M\nadd: [0], 1\nprint_str: 1, \"x\"";
+    assert_matches!(i.eval(text), Err(Error::At(..)));
+    assert_eq!(i.code[0].len(), 4);
+    assert!(!i.mem().var_hash.contains_key("A"));
+    assert_matches!(i.eval("jmp: M"), Err(Error::At(..)));
+    i.eval("add: [0], 1").unwrap();
+    assert_eq!(i.mem_at(0).unwrap(), 6.0);
+}
+
+#[test]
+fn trap(){
+    let mut i = Interpreter::new();
+    i.mem_mut().pmem_allc(&[0.0; 4]);
+    let text = "trap: H\nmov: [9], 1\nexit: 1\nlbl: H\nmov: [5], 7";
+    i.load_str("test", text).unwrap();
+    assert_eq!(i.run().unwrap(), 0);
+    assert_eq!(i.mem_at(0).unwrap(), 9.0);
+    assert_eq!(i.mem_at(1).unwrap(), Error::InvalidMemAccess(9).code() as f64);
+    assert_eq!(i.mem_at(2).unwrap(), 2.0);
+    assert_eq!(i.mem().read_ltl(i.mem_at(3).unwrap() as isize).unwrap(), "test");
+    assert_eq!(i.mem_at(5).unwrap(), 7.0);
+}
+
+#[test]
+fn trap_once(){
+    let mut i = Interpreter::new();
+    i.mem_mut().pmem_allc(&[0.0; 4]);
+    // error in handler is not trapped again
+    i.load_str("test", "trap: H\nlbl: H\ndiv: 1, [9]").unwrap();
+    let e = i.run().unwrap_err();
+    assert_matches!(e, Error::At(ref e, ref s) if matches!(**e, Error::InvalidMemAccess(_)) && s.line == 3);
+    assert_eq!(i.mem_at(2).unwrap(), 3.0);
+}
+
+#[test]
+fn trap_src(){
+    let path = std::env::temp_dir().join("lli_interp_test_trap_src.lli");
+    let _ = std::fs::remove_file(&path);
+    let text = format!("trap: H\nsrc: {}\nexit: 1\nlbl: H\nmov: [5], 7", path.display());
+    let mut i = Interpreter::new();
+    i.mem_mut().pmem_allc(&[0.0; 4]);
+    // missing file
+    i.load_str("test", &text).unwrap();
+    assert_eq!(i.run().unwrap(), 0);
+    // ENOENT
+    assert_eq!(i.mem_at(1).unwrap(), 2.0);
+    assert_eq!(i.mem_at(2).unwrap(), 2.0);
+    assert_eq!(i.mem_at(5).unwrap(), 7.0);
+    // denied by sandbox
+    i.mem_mut().set_sandbox(Some(crate::sandbox::Sandbox::new()));
+    i.load_str("test", &text).unwrap();
+    assert_eq!(i.run().unwrap(), 0);
+    assert_eq!(i.mem_at(1).unwrap(), Error::PermissionDenied(String::new()).code() as f64);
+}
+
+#[test]
+fn trap_in_src(){
+    let path = std::env::temp_dir().join("lli_interp_test_trap_in_src.lli");
+    std::fs::write(&path, "nop\nmov: [9], 1\nexit: 2").unwrap();
+    let text = format!("trap: H\nsrc: {}\nexit: 1\nlbl: H\nmov: [5], 7", path.display());
+    let mut i = Interpreter::new();
+    i.mem_mut().pmem_allc(&[0.0; 4]);
+    i.load_str("test", &text).unwrap();
+    // sourced file is dropped, handler runs in the file that set the trap
+    assert_eq!(i.run().unwrap(), 0);
+    assert_eq!(i.mem_at(5).unwrap(), 7.0);
+    assert_eq!(i.mem_at(2).unwrap(), 2.0);
+    let file = i.mem().read_ltl(i.mem_at(3).unwrap() as isize).unwrap();
+    assert_eq!(file, path.display().to_string());
+    let _ = std::fs::remove_file(&path);
+}
+
+#[test]
+fn runtime_error_col(){
+    let col = |text: &str| {
+        let mut i = Interpreter::new();
+        i.mem_mut().pmem_allc(&[0.0; 2]);
+        i.mem_mut().set_strict(true);
+        i.load_str("test", text).unwrap();
+        match i.run().unwrap_err() {
+            Error::At(_, s) => s.col,
+            e => panic!("{:?}", e),
+        }
+    };
+    // operand read or written
+    assert_eq!(col("mov: [1], [9]"), 10);
+    assert_eq!(col("mov: [9], [1]"), 5);
+    assert_eq!(col("mov: [2], 1.5\nmov: [1], [[2]]"), 10);
+    assert_eq!(col("allc: -1"), 6);
+    assert_eq!(col("cpy: [1], [3], 9"), 10);
+    assert_eq!(col("printf: 1, \"%d %d\", 1, 1.5"), 23);
+    // operand that caused the error
+    assert_eq!(col("div: 1, 0"), 8);
+    assert_eq!(col("sqrt: -1"), 6);
+    // not about an operand, points at op
+    assert_eq!(col("add: 9007199254740991, 1"), 0);
+    // file of src
+    assert_eq!(col("src: /nonexistent/lli.lli"), 5);
+}
+
+fn unwrap_at(e: Error) -> Error{
+    match e {
+        Error::At(e, _) => *e,
+        e => e,
+    }
+}
+
+#[test]
+fn inst_limit(){
+    let mut i = Interpreter::new();
+    i.set_inst_limit(Some(10));
+    i.load_str("test", "lbl: loop\ntrap: h\njmp: loop\nlbl: h").unwrap();
+    i.mem_mut().pmem_allc(&[0.0; 3]);
+    // not handled by trap
+    assert_matches!(i.run().map_err(unwrap_at), Err(Error::InstLimit(10)));
+    let mut i = Interpreter::new();
+    i.set_inst_limit(Some(2));
+    i.load_str("test", "nop\nnop").unwrap();
+    assert_eq!(i.run().unwrap(), 0);
+    // count starts again for the next script
+    let mut i = Interpreter::new();
+    i.set_inst_limit(Some(5));
+    i.load_str("test", "nop\nnop\nnop\nnop\nnop").unwrap();
+    assert_eq!(i.run().unwrap(), 0);
+    i.load_str("test", "nop\nnop\nnop\nnop").unwrap();
+    assert_eq!(i.run().unwrap(), 0);
+}
+
+#[test]
+fn timeout(){
+    let mut i = Interpreter::new();
+    i.set_timeout(Some(Duration::from_millis(10)));
+    i.load_str("test", "lbl: loop\njmp: loop").unwrap();
+    assert_matches!(i.run().map_err(unwrap_at), Err(Error::Timeout(_)));
+}
+
+#[test]
+fn mem_limits(){
+    let mut i = Interpreter::new();
+    i.mem_mut().set_pmem_limit(12);
+    i.load_str("test", "allc: 8\nallc: 1").unwrap();
+    assert_matches!(i.run().map_err(unwrap_at), Err(Error::PmemLimit(12)));
+    assert_eq!(i.mem().pmem_len(), 12);
+    let mut i = Interpreter::new();
+    i.mem_mut().set_pmem_limit(12);
+    i.load_str("test", "alloc: 7\nalloc: 1").unwrap();
+    assert_matches!(i.run().map_err(unwrap_at), Err(Error::PmemLimit(12)));
+    let mut i = Interpreter::new();
+    i.mem_mut().set_nmem_limit(8);
+    // "abc" takes 5 slots after the reserved one, repeated literal is shared
+    i.load_str("test", "cpy: [0], \"abc\", 1\ncpy: [0], \"abc\", 1").unwrap();
+    assert_matches!(i.load_str("test", "cpy: [0], \"d\", 1").map_err(unwrap_at),
+        Err(Error::NmemLimit(8)));
+}
+
+#[test]
+fn src_sandbox(){
+    let d = std::env::temp_dir().join("lli_interp_test_sandbox");
+    std::fs::create_dir_all(d.join("root")).unwrap();
+    std::fs::write(d.join("root/a.lli"), "mov: [0], 7").unwrap();
+    std::fs::write(d.join("b.lli"), "mov: [0], 8").unwrap();
+    let mut sandbox = crate::sandbox::Sandbox::new();
+    sandbox.set_root(d.join("root")).unwrap();
+    let mut i = Interpreter::new();
+    i.mem_mut().set_sandbox(Some(sandbox));
+    let a = d.join("root/a.lli");
+    i.load_str("test", &format!("src: {}", a.to_str().unwrap())).unwrap();
+    i.run().unwrap();
+    assert_eq!(i.mem_at(0).unwrap(), 7.0);
+    let b = d.join("root/../b.lli");
+    i.load_str("test", &format!("src: {}", b.to_str().unwrap())).unwrap();
+    assert_matches!(i.run().map_err(unwrap_at), Err(Error::PermissionDenied(_)));
+    std::fs::remove_dir_all(&d).unwrap();
+}
+
+#[test]
+fn capture_output(){
+    use crate::stream::{Stream, Capture};
+    let mut i = Interpreter::new();
+    let out = Capture::default();
+    i.mem_mut().io_attach(1, Stream::Out(Box::new(out.clone())));
+    i.mem_mut().io_attach(0, Stream::In(Box::new(&b"21"[..])));
+    i.load_str("test", "read_num: 0\nmul: [0], 2\nprint_num: 1, [0]\nprintln: 1, \"!\"").unwrap();
+    i.run().unwrap();
+    assert_eq!(out.to_string_lossy(), "42!\n");
+    // stdin can't be written
+    i.load_str("test", "print_num: 0, 1").unwrap();
+    assert_matches!(i.run().map_err(unwrap_at), Err(Error::BadFileDescriptor(0)));
+}
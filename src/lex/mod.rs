@@ -1,5 +1,4 @@
 use crate::error::Error;
-use super::mem::Mem;
 
 #[derive(Clone, PartialEq, Debug)]
 pub struct HashIdx {
@@ -14,6 +13,7 @@ impl HashIdx{
             idx: i,
         }
     }
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         HashIdx {
             sym: s.to_owned(),
@@ -23,6 +23,7 @@ impl HashIdx{
 }
 
 #[derive(Clone, PartialEq, Debug)]
+#[allow(clippy::enum_variant_names)]
 pub enum Idx {
     Num(isize),
     Idx(Box<Idx>),
@@ -58,17 +59,6 @@ impl Tok{
         }
     }
      
-    pub fn to_str(&self) -> String{
-        match self {
-            Tok::Num(n) => format!("Num({})", n),
-            Tok::Idx(i) => format!("Idx({:?})", i),
-            Tok::Var(s) => format!("Var({})", s.sym),
-            Tok::Ltl(s) => format!("Ltl({})", s),
-            Tok::Sym(s) => format!("Sym({})", s.sym),
-            Tok::Eof => "Eof".to_owned(),
-        }
-    }
-
     fn eat_idx(vec: &[u8]) -> Result<Idx, Error> {
         let len = vec.len();
         if vec[len-1] != b']' {
@@ -86,11 +76,11 @@ impl Tok{
                 let s = unsafe {
                     std::str::from_utf8_unchecked(&vec[2..len-1])
                 };
-                return Ok(Idx::Var(HashIdx::new(s, 0)))
+                Ok(Idx::Var(HashIdx::new(s, 0)))
             },
             // Idx
             b'[' => {
-                return Ok(Idx::Idx(Box::new(Tok::eat_idx(&vec[1..len-1])?)));
+                Ok(Idx::Idx(Box::new(Tok::eat_idx(&vec[1..len-1])?)))
             },
             // Num
             _ => {
@@ -98,9 +88,7 @@ impl Tok{
                     std::str::from_utf8_unchecked(&vec[1..len-1]) 
                 };
                 match s.parse::<isize>() {
-                    Ok(i) => {
-                        return Ok(Idx::Num(i));
-                    },
+                    Ok(i) => Ok(Idx::Num(i)),
                     Err(e) => Err(Error::ParseIdxError(e)),
                 }
             },
@@ -125,7 +113,7 @@ impl Tok{
             },
             // Idx
             b'[' => {
-                Ok(Tok::Idx(Tok::eat_idx(&vec)?))
+                Ok(Tok::Idx(Tok::eat_idx(vec)?))
             },
             // Var
             b'$' => {
@@ -150,250 +138,142 @@ impl Tok{
             }
         }
     }
-
-    pub fn get_value(&self, m: &Mem) -> Result<f64, Error>{
-        match self {
-            Tok::Num(f) => Ok(*f),
-            Tok::Idx(ref i) => {
-                let mut idx = i;
-                let mut layer = 0usize;
-                while let Idx::Idx(a) = idx {
-                    idx = a;
-                    layer += 1;
-                }
-
-                let mut d = match idx {
-                    Idx::Num(n) => m.mem_at(*n)?,
-                    Idx::Var(v) => {
-                        layer += 1;
-                        m.mem_at(m.var_find(v)?)?
-                    },
-                    Idx::Idx(_) => 0f64
-                };
-
-                for _ in 0..layer {
-                    if d != d as isize as f64 {
-                        return Err(Error::NotInterger(d));
-                    }
-                    d = m.mem_at(d as isize)?;
-                }
-                Ok(d)
-            },
-            Tok::Var(n) => m.mem_at(m.var_find(n)?),
-            _ =>
-                Err(Error::WrongArgType(
-                        vec![Tok::NUM_STR, Tok::IDX_STR, Tok::VAR_STR], 
-                        self.to_type_str())),
-        }
-    }
-
-    pub fn get_uint(&self, m: &Mem) -> Result<u64, Error>{
-        let float = self.get_value(m)?;
-        if float != (float as u64) as f64 {
-            return Err(Error::NegativeOrNotInterger(float));
-        }
-        Ok(float as u64)
-    }
-
-    pub fn get_int(&self, m: &Mem) -> Result<i64, Error>{
-        let float = self.get_value(m)?;
-        if float != (float as i64) as f64 {
-            return Err(Error::NotInterger(float));
-        }
-        Ok(float as i64)
-    }
-
-    pub fn get_loc(&self, m: &mut Mem) -> Result<isize, Error> {
-        match self {
-            Tok::Idx(i) => {
-                let mut idx = i;
-                let mut layer: isize = 0;
-                while let Idx::Idx(a) = idx {
-                    idx = a;
-                    layer += 1;
-                }
-
-                let mut l = match idx {
-                    Idx::Num(n) => *n,
-                    Idx::Var(v) => {
-                        let d = m.mem_at(m.var_find(v)?)?;
-                        if d != d as isize as f64 {
-                            return Err(Error::NotInterger(d));
-                        }
-                        d as isize
-                    },
-                    Idx::Idx(_) => 0isize
-                };
-
-                for _ in 0..layer-1 {
-                    let d = m.mem_at(l)?;
-                    if d != d as isize as f64 {
-                        return Err(Error::NotInterger(d));
-                    }
-                    l = d as isize;
-                }
-                Ok(l)
-            },
-            Tok::Var(n) => m.var_find(n),
-            Tok::Ltl(_) =>
-                self.create_ltl(m),
-            _ =>
-                Err(Error::WrongArgType(
-                        vec![Tok::IDX_STR, Tok::VAR_STR, Tok::LTL_STR], 
-                        self.to_type_str())),
-        }
-    }
-
-    pub fn write_value(&self, m: &mut Mem, v: f64) -> Result<(), Error> {
-        let idx = self.get_loc(m)?;
-        if idx < 0 {
-            return Err(Error::WriteToNMem(idx));
-        }
-        m.mem_set(idx, v)
-    }
-
-    // alloc memory for String in nmem
-    // Strings are terminated by two 0f64 consecutively
-    pub fn create_ltl(&self, m: &mut Mem) -> Result<isize, Error> {
-        if let Tok::Ltl(ref s) = self {
-            let idx = m.nmem_len() as isize;
-            // turn string to vec
-            for c in s.as_bytes() {
-                m.nmem_allc(&[*c as f64]);
-            }
-            // null for utf16
-            m.nmem_allc(&[0f64; 2]);
-            // change to negative
-            Ok(-idx)
-        }else{
-            Err(Error::WrongArgType(
-                    vec![Tok::LTL_STR], 
-                    self.to_type_str()))
-        }
-    }
-
-    pub fn get_sym<'a>(&'a self) -> Result<&'a HashIdx, Error> {
-        if let Tok::Sym(ref s) = self {
-            return Ok(s)
-        }else{
-            Err(Error::WrongArgType(
-                    vec![Tok::SYM_STR],
-                    self.to_type_str()))
-        }
-    }
-
 }
 
+// Returns token, offset of token start and bytes consumed.
+// Error comes with offset of the offending byte
 fn eat_token(it: &[u8], len: usize, delim: u8, unexpct: u8)
-    -> Result<(Tok, usize), Error> 
+    -> Result<(Tok, usize, usize), (Error, usize)> 
 {
     #[derive(PartialEq)]
     enum State{
-        WAITING,
-        STARTED(bool),  // bool for isStringLiteral?
-        ENDED,
+        Waiting,
+        Started(bool),  // bool for isStringLiteral?
+        Ended,
     }
     let mut current = Vec::with_capacity(len);
     let mut escaped = false;
-    let mut state = State::WAITING;
+    let mut state = State::Waiting;
+    let mut start = 0;
     let mut len = 0;
     for c in it{
         len += 1;
+        let at = len-1;
         let mut c = *c;
-        if c == b'#' && state != State::STARTED(true) {
+        if c == b'#' && state != State::Started(true) {
             break;
         }else if c == b' ' || c == b'\t' {
-            if state == State::STARTED(false) {
-                state = State::ENDED;
+            if state == State::Started(false) {
+                state = State::Ended;
                 continue;
                 // break;
-            }else if state != State::STARTED(true) {
+            }else if state != State::Started(true) {
                 continue;
             }
         }else if c == delim {
             match state {
-                State::WAITING => 
-                    return Err(Error::EmptyToken),
-                State::STARTED(false) | State::ENDED =>
+                State::Waiting => 
+                    return Err((Error::EmptyToken, at)),
+                State::Started(false) | State::Ended =>
                     break,
-                State::STARTED(true) => (),
+                State::Started(true) => (),
             }
         }else if c == unexpct {
             match state {
-                State::STARTED(true) => (),
+                State::Started(true) => (),
                 _ =>
-                    return Err(Error::UnexpectedChar(unexpct as char)),
+                    return Err((Error::UnexpectedChar(unexpct as char), at)),
             }
-        }else if state == State::ENDED {
-            return Err(Error::NonDelimAfterSymEnd(c as char));
+        }else if state == State::Ended {
+            return Err((Error::NonDelimAfterSymEnd(c as char), at));
         }else if c == b'"'{
             match state {
-                State::WAITING => 
-                    state = State::STARTED(true),
-                State::STARTED(true) =>
+                State::Waiting => 
+                    state = State::Started(true),
+                State::Started(true) =>
                     if !escaped {
-                        state = State::ENDED;
+                        state = State::Ended;
                     },
                 _ => 
-                    return Err(Error::DoubleQuoteInMiddle),
+                    return Err((Error::DoubleQuoteInMiddle, at)),
             }
         }else if c == b'\\' {
-            if state == State::STARTED(true) {
+            if state == State::Started(true) {
                 escaped = !escaped;
                 if !escaped {
                     current.push(c);
                 }
                 continue;
             }
-        }else if state == State::WAITING {
-            state = State::STARTED(false);
-        }else if escaped == true {
+        }else if state == State::Waiting {
+            state = State::Started(false);
+        }else if escaped {
             c = match c {
                 b'n' => b'\n',
                 b't' => b'\t',
-                _ => return Err(Error::UnknownEscapeSequence(c as char)),
+                _ => return Err((Error::UnknownEscapeSequence(c as char), at)),
             }
         }
         escaped = false;
+        if current.is_empty() {
+            start = at;
+        }
         current.push(c);
     }
-    let tok = Tok::from_u8(&current);
-    Ok((tok?, len))
+    match Tok::from_u8(&current) {
+        Ok(tok) => Ok((tok, start, len)),
+        Err(e) => Err((e, start)),
+    }
 }
 
-fn eat_operator(slice: &[u8], len: usize) -> Result<(Tok, usize), Error>  {
+fn eat_operator(slice: &[u8], len: usize) -> Result<(Tok, usize, usize), (Error, usize)>  {
     eat_token(slice, len, b':', b',')
 }
 
-fn eat_args(slice: &[u8], len: usize) -> Result<(Tok, usize), Error>  {
+fn eat_args(slice: &[u8], len: usize) -> Result<(Tok, usize, usize), (Error, usize)>  {
     eat_token(slice, len, b',', b':')
 }
 
 pub fn tokenize(line: &str) -> Result<Vec<Tok>, Error>{
+    match tokenize_cols(line) {
+        Ok((v, _)) => Ok(v),
+        Err((e, _)) => Err(e),
+    }
+}
+
+// Same as tokenize, but also returns byte offset of each token.
+// Error comes with byte offset where lexing failed
+pub fn tokenize_cols(line: &str) -> Result<(Vec<Tok>, Vec<usize>), (Error, usize)>{
     let mut v : Vec<Tok> = Vec::with_capacity(5);
+    let mut cols : Vec<usize> = Vec::with_capacity(5);
     let bytes = line.as_bytes();
     let len = line.len();
     let mut read_len = 0;
     // operator
-    let (op, l) = eat_operator(bytes, len)?;
-    read_len += l;
+    let (op, s, l) = eat_operator(bytes, len)?;
     v.push(match op {
         Tok::Sym(_) => 
             op,
         Tok::Eof =>
-            return Ok(v),
+            return Ok((v, cols)),
         _ => 
-            return Err(Error::WrongTokTypeForOp(op.to_type_str())),
+            return Err((Error::WrongTokTypeForOp(op.to_type_str()), s)),
     });
+    cols.push(s);
+    read_len += l;
     // args
     loop {
-        let (arg, l) = eat_args(&bytes[read_len..], len-read_len)?;
+        let (arg, s, l) = match eat_args(&bytes[read_len..], len-read_len) {
+            Ok(r) => r,
+            Err((e, at)) => return Err((e, read_len+at)),
+        };
         v.push(match arg {
             Tok::Eof =>
-                return Ok(v),
+                return Ok((v, cols)),
             _ =>
                 arg,
         });
+        cols.push(read_len+s);
         read_len += l;
     }
 }
@@ -0,0 +1,20 @@
+pub mod error;
+pub mod lex;
+pub mod code;
+pub mod mem;
+pub mod op;
+mod interp;
+pub mod debug;
+pub mod repl;
+pub mod trace;
+pub mod profile;
+pub mod sandbox;
+pub mod stream;
+pub use interp::{Interpreter, State};
+pub use error::Error;
+#[macro_use]
+extern crate num_derive;
+// used by tests
+#[cfg(test)]
+#[macro_use]
+extern crate matches;
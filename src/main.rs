@@ -1,211 +1,221 @@
-mod error;
-mod lex;
-mod code;
-mod mem;
-mod op;
-use num_traits::FromPrimitive;
-use std::io::{self, BufRead};
-use std::fs::File;
 use std::env;
-use ahash::AHashMap;
-use mem::Mem;
-use code::Code;
-use lex::Tok;
-use error::Error;
-#[macro_use]
-extern crate num_derive;
-// used by tests
-#[macro_use]
-extern crate matches;
+use lli::{Interpreter, mem, sandbox::Sandbox, stream::Stream, debug::Debugger, repl::Repl, trace::Tracer, profile::{Profiler, Format}};
 
 static ERROR_MSG_LEVEL: usize = 1;
 
-fn assign_opcode(
-    op_idx_table: &AHashMap<&'static str, usize>, 
-    c: &mut Code,
-    t: &mut [Tok]
-) -> Result<usize, Error> 
-{
-    if let Tok::Sym(ref mut n) = t[0] {
-        // lookup and assign opcode
-        let s: &str = &n.sym;
-        n.idx = c.func_idx_push(
-            match op_idx_table.get(s) {
-                Some(i) => *i,
-                None => return Err(Error::UnknownOp(s.to_string())),
-            });
-        Ok(n.idx)
-    }else{
-        return Err(Error::WrongTokTypeForOp(t[0].to_type_str()))
-    }
+fn usage(msg: &str) -> ! {
+    eprintln!("{}", msg);
+    std::process::exit(1);
 }
 
-fn create_symbol_table(
-    opcode: usize,
-    m: &mut Mem, 
-    c: &mut Code, 
-    t: &mut Vec<Tok>
-) -> Result<(), Error>
-{
-    match FromPrimitive::from_usize(opcode).unwrap() {
-        op::Opcode::Lbl | op::Opcode::Als => if let Tok::Sym(ref mut hi) = t[1] {
-            hi.idx = match m.label_hash.get(&hi.sym) {
-                Some(i) => *i,
-                None => {
-                    let idx = m.label_add(c.len()+1);
-                    m.label_hash.insert(hi.sym.to_owned(), idx);
-                    idx
-                },
-            };
-        },
-        op::Opcode::Var => if let Tok::Sym(ref mut hi) = t[1] {
-            hi.idx = match m.var_hash.get(&hi.sym) {
-                Some(i) => *i,
-                None => {
-                    let idx = m.var_add(0);
-                    m.var_hash.insert(hi.sym.to_owned(), idx);
-                    idx
-                }
-            }
-        },
-        _ => (),
-    };
-    Ok(())
-}
-
-fn replace_lbl(tok: &mut Tok, m: &Mem)  -> Result<(), Error>{
-    if let Tok::Sym(ref mut hi) = tok {
-        hi.idx = match m.label_hash.get(&hi.sym) {
-            Some(i) => *i,
-            None =>
-                return Err(Error::UnknownLabel(hi.sym.clone())),
-        }
+fn parse_arg<T: std::str::FromStr>(arg: Option<String>, flag: &str, expect: &str) -> T {
+    match arg.map(|s| s.parse::<T>()) {
+        Some(Ok(v)) => v,
+        _ => usage(&format!("{} expects {}", flag, expect)),
     }
-    Ok(())
 }
 
-// loop through all lines to replace symbols
-fn replace_sym(m: &Mem, c: &mut Code) -> Result<(), Error> {
-    for i in 0..c.len() {
-        let line = c.at_mut(i).unwrap();
-        if let Tok::Sym(ref hi) = line[0] {
-            match FromPrimitive::from_usize(hi.idx).unwrap() {
-                op::Opcode::Jmp => replace_lbl(&mut line[1], m)?,
-                op::Opcode::Jc | op::Opcode::Als => replace_lbl(&mut line[2], m)?,
-                _ => ()
-            }
-            for a in &mut line[1..] {
-                // Var or VarIdx
-                if let Tok::Var(ref mut hi) = a {
-                    hi.idx = match m.var_hash.get(&hi.sym) {
-                        Some(i) => *i,
-                        None => 
-                            return Err(Error::UndefinedVar(hi.sym.to_owned())),
-                    };
-                    continue;
-                }else if let Tok::Idx(ref mut i) = a {
-                    let mut idx = i;
-                    while let lex::Idx::Idx(b) = idx {
-                        idx = b;
-                    }
-                    if let lex::Idx::Var(v) = idx {
-                        v.idx = match m.var_hash.get(&v.sym) {
-                            Some(c) => *c,
-                            None =>
-                                return Err(Error::UndefinedVar(v.sym.to_owned())),
-                        }
-                    }
+fn main() {
+    let mut file_name = None;
+    let mut call_depth = mem::DEFAULT_CALL_DEPTH;
+    let mut debug = false;
+    let mut repl = false;
+    let mut strict = false;
+    let mut nonfatal_io = false;
+    let mut max_pmem = usize::MAX;
+    let mut max_nmem = usize::MAX;
+    let mut max_fds = mem::DEFAULT_FD_LIMIT;
+    let mut max_insts = None;
+    let mut timeout = None;
+    let mut sandbox: Option<Sandbox> = None;
+    let mut src_root = None;
+    let mut tracer: Option<Tracer> = None;
+    let mut profiler: Option<Profiler> = None;
+    let mut profile_file = None;
+    let mut args = env::args().skip(1);
+    while let Some(a) = args.next() {
+        match a.as_str() {
+            "--call-depth" => {
+                call_depth = match args.next().map(|s| s.parse::<usize>()) {
+                    Some(Ok(d)) => d,
+                    _ => usage("--call-depth expects an unsigned integer"),
+                };
+            },
+            "--trace" => if tracer.is_none() {
+                tracer = Some(Tracer::new(Box::new(std::io::stderr())));
+            },
+            "--trace-file" => {
+                let f = match args.next().map(std::fs::File::create) {
+                    Some(Ok(f)) => f,
+                    Some(Err(e)) => usage(&format!("--trace-file: {}", e)),
+                    None => usage("--trace-file expects a file name"),
+                };
+                let filter = tracer.take().map(|t| (t.lines, t.ops));
+                let t = tracer.get_or_insert(Tracer::new(Box::new(std::io::BufWriter::new(f))));
+                if let Some((lines, ops)) = filter {
+                    t.lines = lines;
+                    t.ops = ops;
                 }
-            }
+            },
+            "--trace-lines" => {
+                let range = args.next().and_then(|r| {
+                    let (from, to) = r.split_once('-')?;
+                    Some(from.parse::<usize>().ok()?..=to.parse::<usize>().ok()?)
+                });
+                match range {
+                    Some(r) => tracer.get_or_insert(Tracer::new(Box::new(std::io::stderr()))).lines = Some(r),
+                    None => usage("--trace-lines expects a range like 10-20"),
+                }
+            },
+            "--profile" => {
+                profiler.get_or_insert_with(Profiler::new);
+            },
+            "--profile-format" => {
+                let format = match args.next().as_deref() {
+                    Some("text") => Format::Text,
+                    Some("tsv") => Format::Tsv,
+                    _ => usage("--profile-format expects text or tsv"),
+                };
+                profiler.get_or_insert_with(Profiler::new).format = format;
+            },
+            "--profile-file" => match args.next() {
+                Some(f) => {
+                    profiler.get_or_insert_with(Profiler::new);
+                    profile_file = Some(f);
+                },
+                None => usage("--profile-file expects a file name"),
+            },
+            "--trace-ops" => match args.next() {
+                Some(ops) => tracer.get_or_insert(Tracer::new(Box::new(std::io::stderr())))
+                    .ops = ops.split(',').map(|s| s.to_owned()).collect(),
+                None => usage("--trace-ops expects a comma separated list of ops"),
+            },
+            "--max-pmem" => max_pmem = parse_arg(args.next(), &a, "an unsigned integer"),
+            "--max-nmem" => max_nmem = parse_arg(args.next(), &a, "an unsigned integer"),
+            "--max-fds" => max_fds = parse_arg(args.next(), &a, "an unsigned integer"),
+            "--max-insts" => max_insts = Some(parse_arg(args.next(), &a, "an unsigned integer")),
+            "--timeout" => {
+                let secs: f64 = parse_arg(args.next(), &a, "seconds");
+                match std::time::Duration::try_from_secs_f64(secs) {
+                    Ok(d) => timeout = Some(d),
+                    Err(_) => usage("--timeout expects seconds"),
+                }
+            },
+            "--sandbox" => {
+                sandbox.get_or_insert_with(Sandbox::new);
+            },
+            "--allow-read" | "--allow-write" | "--scratch" => {
+                let dir = match args.next() {
+                    Some(d) => d,
+                    None => usage(&format!("{} expects a directory", a)),
+                };
+                let s = sandbox.get_or_insert_with(Sandbox::new);
+                let r = match a.as_str() {
+                    "--allow-read" => s.allow_read(&dir),
+                    "--allow-write" => s.allow_write(&dir),
+                    _ => s.allow_scratch(&dir),
+                };
+                if r.is_err() {
+                    usage(&format!("{}: {} is not a directory", a, dir));
+                }
+            },
+            "--src-root" => match args.next() {
+                Some(d) => {
+                    sandbox.get_or_insert_with(Sandbox::new);
+                    src_root = Some(d);
+                },
+                None => usage("--src-root expects a directory"),
+            },
+            "--debug" => debug = true,
+            "--repl" => repl = true,
+            "--strict" => strict = true,
+            "--nonfatal-io" => nonfatal_io = true,
+            _ => file_name = Some(a),
         }
     }
-    Ok(())
-}
-
-fn preprocess(
-    op_idx_table: &AHashMap<&'static str, usize>, 
-    m: &mut Mem, 
-    c: &mut Code,
-    mut t: Vec<Tok>
-) -> Result<(), Error> {
-    // skip empty lines
-    if t.len() == 0 {
-        return Ok(());
+    // each mode runs the script its own way
+    let modes = [repl, debug, tracer.is_some(), profiler.is_some()];
+    if modes.iter().filter(|m| **m).count() > 1 {
+        usage("--repl, --debug, --trace and --profile can't be combined");
     }
-    let opcode = assign_opcode(op_idx_table, c, &mut t)?;
-    // create symbol table
-    create_symbol_table(opcode, m, c, &mut t)?;
-    c.push(t);
-    Ok(())
-}
-
-fn read_from_file(
-    file_name: &str, 
-    m: &mut Mem, 
-    code: &mut Code, 
-    op_idx_table: &AHashMap<&'static str, usize>, 
-) -> Result<(), Error> {
-    let file = File::open(file_name)
-                    .unwrap_or_else(|e| {
-                        eprintln!("{}", e);
-                        std::process::exit(1);
-                    });
-    let lines = io::BufReader::new(file).lines();
-    for line in lines {
-        match line {
-            Ok(s) => {
-                let t = match lex::tokenize(&s){
-                    Ok(t) => t,
-                    Err(e) => { 
+    let mut interp = Interpreter::new();
+    interp.mem_mut().set_call_depth(call_depth);
+    interp.mem_mut().set_strict(strict);
+    interp.mem_mut().set_nonfatal_io(nonfatal_io);
+    interp.mem_mut().set_pmem_limit(max_pmem);
+    interp.mem_mut().set_nmem_limit(max_nmem);
+    interp.mem_mut().set_fd_limit(max_fds);
+    interp.set_inst_limit(max_insts);
+    interp.set_timeout(timeout);
+    if let Some(mut s) = sandbox {
+        // src is limited to the directory of the script by default
+        let root = src_root.unwrap_or_else(|| {
+            file_name.as_ref()
+                .and_then(|f| std::path::Path::new(f).parent())
+                .filter(|p| !p.as_os_str().is_empty())
+                .map_or(".".to_owned(), |p| p.to_string_lossy().into_owned())
+        });
+        if s.set_root(&root).is_err() {
+            usage(&format!("--src-root: {} is not a directory", root));
+        }
+        interp.mem_mut().set_sandbox(Some(s));
+    }
+    let file_name = match file_name {
+        Some(f) if !repl => f,
+        _ => {
+            // file given with --repl is sourced first
+            let mut r = Repl::new();
+            if let Some(f) = file_name {
+                let mut out = String::new();
+                let loaded = r.src(&mut interp, &f, &mut out);
+                print!("{}", out);
+                match loaded {
+                    Ok(Some(exit_code)) => std::process::exit(exit_code),
+                    Ok(None) => (),
+                    Err(e) => {
                         e.print(ERROR_MSG_LEVEL);
                         std::process::exit(1);
                     },
-                };
-                // preprocess and push t to code
-                preprocess(op_idx_table, m, code, t).unwrap();
-            },
-            Err(e) => {
-                eprintln!("{}", e);
-                std::process::exit(1);
-            },
-        };
-    }
-    replace_sym(m, code)
-}
-
-fn run(
-    m: &mut Mem, 
-    code: &mut Code, 
-    op_idx_table: &AHashMap<&'static str, usize>, 
-    op_vec: &[op::OpFunc]
-) -> Result<(), Error>
-{
-    while code.ptr() < code.len() {
-        // the 2 unwrap_or_else closures have different return value
-        op::exec(op_vec, m, code)?.respond(m, code, op_idx_table, op_vec)?;
+                }
+            }
+            let stdin = std::io::stdin();
+            let exit_code = r.run(&mut interp, &mut stdin.lock(), &mut std::io::stdout())
+                .unwrap_or_else(|e| {
+                    e.print(ERROR_MSG_LEVEL);
+                    1
+                });
+            std::process::exit(exit_code);
+        },
     };
-    Ok(())
-}
-
-fn main() {
-    let args: Vec<String> = env::args().collect();
-    if args.len() == 1 {
-        return;
+    if !debug {
+        // flushed before exit, interactive sessions keep stdout line buffered
+        let out = std::io::BufWriter::new(std::io::stdout());
+        interp.mem_mut().io_attach(1, Stream::Out(Box::new(out)));
     }
-    let mut m = mem::Mem::new();
-    let mut code = code::Code::new();
-    let mut op_idx_table: AHashMap<&'static str, usize> = AHashMap::new();
-    let mut op_vec: Vec<op::OpFunc> = Vec::new();
-
-    op::init_op_table(&mut op_idx_table, &mut op_vec);
-    read_from_file(&args[1], &mut m, &mut code, &mut op_idx_table)
-        .unwrap_or_else(|e| {
-            e.print(ERROR_MSG_LEVEL);
-            std::process::exit(1);
-    });
-    run(&mut m, &mut code, &op_idx_table, &op_vec)
-        .unwrap_or_else(|e| {
-            e.print(ERROR_MSG_LEVEL);
-            std::process::exit(1);
+    let r = interp.load_file(&file_name)
+        .and_then(|_| if debug {
+            let stdin = std::io::stdin();
+            Debugger::new().run(&mut interp, &mut stdin.lock(), &mut std::io::stdout())
+        }else if let Some(t) = &mut tracer {
+            t.run(&mut interp)
+        }else if let Some(p) = &mut profiler {
+            // report is written even if the script fails
+            let r = p.run(&mut interp);
+            let report = match &profile_file {
+                Some(f) => std::fs::File::create(f)
+                    .map_err(lli::Error::IoError)
+                    .and_then(|mut f| p.report(&interp, &mut f)),
+                None => p.report(&interp, &mut std::io::stderr()),
+            };
+            report.and(r)
+        }else{
+            interp.run()
+        });
+    // script output goes before error message
+    let r = interp.mem_mut().io_flush().and(r);
+    let exit_code = r.unwrap_or_else(|e| {
+        e.print(ERROR_MSG_LEVEL);
+        1
     });
+    std::process::exit(exit_code);
 }
@@ -1,8 +1,8 @@
 use ahash::AHashMap;
+use std::collections::{BTreeMap, BTreeSet};
 use super::error::Error;
-use crate::lex::HashIdx;
-use std::os::unix::io::{ FromRawFd, IntoRawFd };
-use std::fs::File;
+use super::sandbox::Sandbox;
+use super::stream::Stream;
 
 #[derive(Debug)]
 pub struct Mem{
@@ -12,30 +12,77 @@ pub struct Mem{
     var: Vec<isize>,
     pub label_hash: AHashMap<String, usize>,
     label: Vec<usize>,
-    pub fd: Vec<bool>,
+    ltl_hash: AHashMap<String, isize>,
+    sym: Vec<String>,
+    ret: Vec<usize>,
+    ret_limit: usize,
+    strict: bool,  // numeric faults are errors instead of inf or NaN
+    nonfatal_io: bool,  // io errors of sys ops are written to [1]
+    trap: Option<(usize, usize)>,  // error handler label, depth of code stack when registered
+    heap: BTreeMap<usize, Block>,  // base -> block, in pmem
+    freed: BTreeSet<usize>,  // bases freed and not allocated again
+    pmem_limit: usize,  // max slots, checked by allc and heap
+    nmem_limit: usize,  // max slots, checked while compiling literals
+    fd_limit: usize,  // max open fds, including stdin, stdout and stderr
+    sandbox: Option<Sandbox>,  // filesystem policy, no restriction if None
+    io: Vec<Option<Stream>>,  // fd -> stream
+}
+
+// Heap block. Slot before base is header, holding size for scripts to read.
+// Blocks are tracked here, header in pmem may be overwritten
+#[derive(Clone, Copy, Debug)]
+struct Block{
+    size: usize,
+    free: bool,
+}
+
+// Sizes of the tables filled while parsing, see Mem::rollback
+#[derive(Clone, Copy, Debug)]
+pub struct Mark{
+    var: usize,
+    label: usize,
+    sym: usize,
+    nmem: usize,
+}
+
+pub const DEFAULT_CALL_DEPTH: usize = 10000;
+pub const DEFAULT_FD_LIMIT: usize = 1024;
+
+impl Default for Mem{
+    fn default() -> Self {
+        Mem::new()
+    }
 }
 
 impl Mem{
     pub fn new() -> Mem {
-        let fd_limit = 1024;
         let mut m = Mem {
-            pmem: vec![0.0],
+            pmem: vec![0.0; 4],  // [0] op result, [1] error code, [2] and [3] error line and file for trap
             nmem: Vec::with_capacity(10000),
             var_hash: AHashMap::new(),
             var: Vec::with_capacity(100000),
             label_hash: AHashMap::new(),
             label: Vec::with_capacity(100000),
-            fd: vec![false; fd_limit],
+            ltl_hash: AHashMap::new(),
+            sym: Vec::new(),
+            ret: Vec::new(),
+            ret_limit: DEFAULT_CALL_DEPTH,
+            strict: false,
+            nonfatal_io: false,
+            trap: None,
+            heap: BTreeMap::new(),
+            freed: BTreeSet::new(),
+            pmem_limit: usize::MAX,
+            nmem_limit: usize::MAX,
+            fd_limit: DEFAULT_FD_LIMIT,
+            sandbox: None,
+            io: vec![
+                Some(Stream::In(Box::new(std::io::stdin()))),
+                Some(Stream::Out(Box::new(std::io::stdout()))),
+                Some(Stream::Out(Box::new(std::io::stderr()))),
+            ],
         };
         m.nmem.push(0.0);
-        unsafe {
-            File::from_raw_fd(0).into_raw_fd();
-            m.fd[0] = true;
-            File::from_raw_fd(1).into_raw_fd();
-            m.fd[1] = true;
-            File::from_raw_fd(2).into_raw_fd();
-            m.fd[2] = true;
-        }
         m
     }
     pub fn mem_at(&self, i: isize) -> Result<f64, Error>{
@@ -54,16 +101,17 @@ impl Mem{
     }
     pub fn pmem_at(&self, i: usize) -> Result<f64, Error>{
         if i >= self.pmem.len() {
-            Err(Error::InvalidMemAccess(-(i as isize)))
+            Err(Error::InvalidMemAccess(i as isize))
         }else{
             Ok(self.pmem[i])
         }
     }
     pub fn pmem_set(&mut self, i: usize, v: f64) -> Result<(), Error>{
         if i >= self.pmem.len() {
-            Err(Error::InvalidMemAccess(-(i as isize)))
+            Err(Error::InvalidMemAccess(i as isize))
         }else{
-            Ok(self.pmem[i] = v)
+            self.pmem[i] = v;
+            Ok(())
         }
     }
     pub fn pmem_len(&self) -> usize{
@@ -72,6 +120,135 @@ impl Mem{
     pub fn pmem_allc(&mut self, v: &[f64]) {
         self.pmem.extend_from_slice(v);
     }
+
+    // Allocate block of zeroed slots in pmem, first fit among freed blocks.
+    // Returns base index
+    pub fn heap_alloc(&mut self, size: usize) -> Result<usize, Error> {
+        let fit = self.heap.iter()
+            .find(|(_, b)| b.free && b.size >= size)
+            .map(|(base, _)| *base);
+        let base = match fit {
+            Some(base) => base,
+            None => {
+                // header and data at the end of pmem
+                self.pmem_reserve(size + 1)?;
+                let base = self.pmem.len() + 1;
+                self.pmem.resize(base + size, 0.0);
+                self.heap.insert(base, Block { size, free: false });
+                base
+            },
+        };
+        let b = self.heap.get_mut(&base).unwrap();
+        b.free = false;
+        let end = base + b.size;
+        for v in &mut self.pmem[base..end] {
+            *v = 0.0;
+        }
+        self.heap_split(base, size);
+        self.heap_claim(base, size);
+        Ok(base)
+    }
+
+    pub fn heap_free(&mut self, ptr: isize) -> Result<(), Error> {
+        let base = self.heap_block(ptr)?;
+        self.heap_release(base);
+        self.freed.insert(base);
+        Ok(())
+    }
+
+    // Resize block, moving it if it can't grow in place.
+    // Contents are kept up to the smaller size, new slots are zeroed.
+    // Returns base index
+    pub fn heap_realloc(&mut self, ptr: isize, size: usize) -> Result<usize, Error> {
+        let base = self.heap_block(ptr)?;
+        let old = self.heap[&base].size;
+        if size <= old {
+            self.heap_split(base, size);
+            return Ok(base);
+        }
+        let next = base + old + 1;
+        let next_free = self.heap.get(&next).filter(|b| b.free).map(|b| b.size);
+        if base + old == self.pmem.len() {
+            self.pmem_reserve(size - old)?;
+            self.pmem.resize(base + size, 0.0);
+        }else if let Some(n) = next_free.filter(|n| old + 1 + n >= size) {
+            // absorb next free block
+            self.heap.remove(&next);
+            self.heap.get_mut(&base).unwrap().size = old + 1 + n;
+            for v in &mut self.pmem[base+old..base+old+1+n] {
+                *v = 0.0;
+            }
+        }else{
+            let new = self.heap_alloc(size)?;
+            self.pmem.copy_within(base..base+old, new);
+            self.heap_release(base);
+            self.freed.insert(base);
+            return Ok(new);
+        }
+        self.heap.get_mut(&base).unwrap().size = size.max(self.heap[&base].size);
+        self.heap_split(base, size);
+        self.heap_claim(base, size);
+        Ok(base)
+    }
+
+    // freed bases inside an allocated block are no longer double frees
+    fn heap_claim(&mut self, base: usize, size: usize) {
+        let stale: Vec<usize> = self.freed.range(base-1..base+size).copied().collect();
+        for i in stale {
+            self.freed.remove(&i);
+        }
+    }
+
+    // base of block in use at ptr
+    fn heap_block(&self, ptr: isize) -> Result<usize, Error> {
+        if ptr < 0 {
+            return Err(Error::NotHeapPtr(ptr));
+        }
+        match self.heap.get(&(ptr as usize)) {
+            Some(b) if !b.free => Ok(ptr as usize),
+            _ if self.freed.contains(&(ptr as usize)) => Err(Error::DoubleFree(ptr)),
+            _ => Err(Error::NotHeapPtr(ptr)),
+        }
+    }
+
+    // Shrink block to size, rest becomes a free block if it can hold a header
+    fn heap_split(&mut self, base: usize, size: usize) {
+        let b = self.heap[&base];
+        if b.size > size {
+            let rest = base + size + 1;
+            self.heap.get_mut(&base).unwrap().size = size;
+            self.heap.insert(rest, Block { size: b.size - size - 1, free: false });
+            self.heap_release(rest);
+        }
+        self.pmem[base-1] = self.heap[&base].size as f64;
+    }
+
+    // Mark block free and merge it with free neighbours.
+    // Memory at the end of pmem is given back
+    fn heap_release(&mut self, base: usize) {
+        let mut base = base;
+        let mut size = self.heap[&base].size;
+        let next = base + size + 1;
+        if let Some(n) = self.heap.get(&next).filter(|b| b.free).map(|b| b.size) {
+            self.heap.remove(&next);
+            size += n + 1;
+        }
+        if let Some((p, b)) = self.heap.range(..base).next_back().map(|(p, b)| (*p, *b)) {
+            if b.free && p + b.size + 1 == base {
+                self.heap.remove(&base);
+                size += b.size + 1;
+                base = p;
+            }
+        }
+        if base + size == self.pmem.len() {
+            self.heap.remove(&base);
+            self.pmem.truncate(base-1);
+        }else{
+            self.heap.insert(base, Block { size, free: true });
+            self.pmem[base-1] = size as f64;
+        }
+    }
+
     pub fn nmem_at(&self, i: usize) -> Result<f64, Error>{
         if i >= self.nmem.len() {
             Err(Error::InvalidMemAccess(-(i as isize)))
@@ -83,7 +260,8 @@ impl Mem{
         if i >= self.nmem.len() {
             Err(Error::InvalidMemAccess(-(i as isize)))
         }else{
-            Ok(self.nmem[i] = v)
+            self.nmem[i] = v;
+            Ok(())
         }
     }
     pub fn nmem_len(&self) -> usize{
@@ -99,14 +277,46 @@ impl Mem{
     pub fn var_set(&mut self, var: usize, idx: isize){
         self.var[var] = idx;
     }
-    pub fn var_find(&self, hi: &HashIdx) -> Result<isize, Error>{
-        match self.var.get(hi.idx) {
+    pub fn var_find(&self, var: usize) -> Result<isize, Error>{
+        match self.var.get(var) {
             Some(v) => Ok(*v),
-            None => Err(Error::UndefinedVar(hi.sym.to_string()))
+            None => Err(Error::UndefinedVar(name_of(&self.var_hash, var)))
+        }
+    }
+
+    // Alloc memory for String in nmem, called while compiling.
+    // Identical literals share the same memory.
+    // Strings are terminated by two 0f64 consecutively
+    pub fn ltl_intern(&mut self, s: &str) -> isize {
+        if let Some(i) = self.ltl_hash.get(s) {
+            return *i;
         }
+        // change to negative
+        let idx = -(self.nmem_len() as isize);
+        // turn string to vec
+        let v: Vec<f64> = s.bytes().map(|c| c as f64).collect();
+        self.nmem_allc(&v);
+        // null for utf16
+        self.nmem_allc(&[0f64; 2]);
+        self.ltl_hash.insert(s.to_owned(), idx);
+        idx
+    }
+    // symbol pool, filled while compiling
+    pub fn sym_add(&mut self, s: &str) -> usize {
+        self.sym.push(s.to_owned());
+        self.sym.len()-1
+    }
+    pub fn sym_at(&self, sym: usize) -> &str {
+        &self.sym[sym]
     }
 
     pub fn read_ltl(&self, i: isize) -> Result<String, Error> {
+        let v = self.ltl_bytes(i)?;
+        Ok(String::from_utf8_lossy(&v).into_owned())
+    }
+    // Bytes of string starting at i, without the terminating two 0f64.
+    // Works for both nmem and pmem
+    pub fn ltl_bytes(&self, i: isize) -> Result<Vec<u8>, Error> {
         let mut v : Vec<u8> = Vec::new();
         let mut zero_count = 0;
         let mut i = i;
@@ -115,7 +325,7 @@ impl Mem{
             if c == 0.0 {
                 if zero_count == 1{
                     v.pop();
-                    return Ok(String::from_utf8(v).unwrap());
+                    return Ok(v);
                 }
                 zero_count += 1;
             }else{
@@ -132,27 +342,172 @@ impl Mem{
     pub fn label_set(&mut self, lbl: usize, line: usize){
         self.label[lbl] = line;
     }
-    pub fn label_find(&self, hi: &HashIdx) -> Result<usize, Error>{
-        match self.label.get(hi.idx) {
+    pub fn label_find(&self, lbl: usize) -> Result<usize, Error>{
+        match self.label.get(lbl) {
             Some(i) => Ok(*i),
-            None => Err(Error::UnknownLabel(hi.sym.to_string())),
+            None => Err(Error::UnknownLabel(name_of(&self.label_hash, lbl))),
         }
     }
+
+    pub fn label_name(&self, lbl: usize) -> String{
+        name_of(&self.label_hash, lbl)
+    }
+
+    pub fn mark(&self) -> Mark{
+        Mark {
+            var: self.var.len(),
+            label: self.label.len(),
+            sym: self.sym.len(),
+            nmem: self.nmem.len(),
+        }
+    }
+    // Forget vars, labels, symbols and literals added after mark,
+    // used when parsing fails halfway
+    pub fn rollback(&mut self, mark: Mark){
+        self.var.truncate(mark.var);
+        self.var_hash.retain(|_, i| *i < mark.var);
+        self.label.truncate(mark.label);
+        self.label_hash.retain(|_, i| *i < mark.label);
+        self.sym.truncate(mark.sym);
+        self.nmem.truncate(mark.nmem);
+        self.ltl_hash.retain(|_, i| ((-*i) as usize) < mark.nmem);
+    }
+
+    // return stack used by call and ret
+    pub fn ret_push(&mut self, line: usize) -> Result<(), Error>{
+        if self.ret.len() >= self.ret_limit {
+            return Err(Error::CallStackOverflow(self.ret_limit));
+        }
+        self.ret.push(line);
+        Ok(())
+    }
+    pub fn ret_pop(&mut self) -> Result<usize, Error>{
+        match self.ret.pop() {
+            Some(line) => Ok(line),
+            None => Err(Error::CallStackUnderflow),
+        }
+    }
+    // limits for sandboxed scripts
+    pub fn set_pmem_limit(&mut self, slots: usize){
+        self.pmem_limit = slots;
+    }
+    pub fn set_nmem_limit(&mut self, slots: usize){
+        self.nmem_limit = slots;
+    }
+    pub fn set_fd_limit(&mut self, fds: usize){
+        self.fd_limit = fds;
+    }
+    pub fn set_sandbox(&mut self, sandbox: Option<Sandbox>){
+        self.sandbox = sandbox;
+    }
+    pub fn sandbox(&self) -> Option<&Sandbox>{
+        self.sandbox.as_ref()
+    }
+    // check that n more slots fit in pmem
+    pub fn pmem_reserve(&self, n: usize) -> Result<(), Error>{
+        if self.pmem.len().saturating_add(n) > self.pmem_limit {
+            return Err(Error::PmemLimit(self.pmem_limit));
+        }
+        Ok(())
+    }
+    // check that literal fits in nmem, interned literals always do
+    pub fn ltl_reserve(&self, s: &str) -> Result<(), Error>{
+        if !self.ltl_hash.contains_key(s) && self.nmem.len().saturating_add(s.len() + 2) > self.nmem_limit {
+            return Err(Error::NmemLimit(self.nmem_limit));
+        }
+        Ok(())
+    }
+    // Attach stream to fd, replacing the one there if any.
+    // Embedders use it to redirect stdin, stdout or stderr
+    pub fn io_attach(&mut self, fd: usize, s: Stream) -> Option<Stream>{
+        if fd >= self.io.len() {
+            self.io.resize_with(fd + 1, || None);
+        }
+        self.io[fd].replace(s)
+    }
+    // check that one more fd can be opened
+    pub fn io_reserve(&self) -> Result<(), Error>{
+        if self.io.iter().filter(|s| s.is_some()).count() >= self.fd_limit {
+            return Err(Error::FdLimit(self.fd_limit));
+        }
+        Ok(())
+    }
+    // Attach stream to the lowest free fd, checked against fd limit
+    pub fn io_open(&mut self, s: Stream) -> Result<usize, Error>{
+        self.io_reserve()?;
+        let fd = self.io.iter().position(|s| s.is_none()).unwrap_or(self.io.len());
+        self.io_attach(fd, s);
+        Ok(fd)
+    }
+    // Detach stream, closing it when dropped
+    pub fn io_close(&mut self, fd: i32) -> Result<Stream, Error>{
+        match self.io.get_mut(fd as usize).and_then(|s| s.take()) {
+            Some(s) => Ok(s),
+            None => Err(Error::BadFileDescriptor(fd)),
+        }
+    }
+    pub fn io_reader(&mut self, fd: i32) -> Result<&mut dyn std::io::Read, Error>{
+        match self.io.get_mut(fd as usize).and_then(|s| s.as_mut()).and_then(|s| s.reader()) {
+            Some(r) => Ok(r),
+            None => Err(Error::BadFileDescriptor(fd)),
+        }
+    }
+    pub fn io_writer(&mut self, fd: i32) -> Result<&mut dyn std::io::Write, Error>{
+        match self.io.get_mut(fd as usize).and_then(|s| s.as_mut()).and_then(|s| s.writer()) {
+            Some(w) => Ok(w),
+            None => Err(Error::BadFileDescriptor(fd)),
+        }
+    }
+    // Flush every output stream, returns the first error
+    pub fn io_flush(&mut self) -> Result<(), Error>{
+        let mut r = Ok(());
+        for w in self.io.iter_mut().flatten().filter_map(|s| s.writer()) {
+            if let Err(e) = w.flush() {
+                r = r.and(Err(Error::IoError(e)));
+            }
+        }
+        r
+    }
+
+    pub fn set_call_depth(&mut self, depth: usize){
+        self.ret_limit = depth;
+    }
+
+    pub fn strict(&self) -> bool{
+        self.strict
+    }
+    pub fn set_strict(&mut self, strict: bool){
+        self.strict = strict;
+    }
+    pub fn nonfatal_io(&self) -> bool{
+        self.nonfatal_io
+    }
+    pub fn set_nonfatal_io(&mut self, nonfatal: bool){
+        self.nonfatal_io = nonfatal;
+    }
+
+    // error handler registered by trap op
+    pub fn set_trap(&mut self, trap: Option<(usize, usize)>){
+        self.trap = trap;
+    }
+    pub fn take_trap(&mut self) -> Option<(usize, usize)>{
+        self.trap.take()
+    }
 }
 
-pub fn idx_incr(i: &mut isize, delta: isize){
-    if *i < 0 {
-        *i -= delta;
-    }else {
-        *i += delta;
+// reverse lookup of symbol name, only used for error messages and tracing
+fn name_of(h: &AHashMap<String, usize>, idx: usize) -> String {
+    match h.iter().find(|(_, i)| **i == idx) {
+        Some((s, _)) => s.clone(),
+        None => idx.to_string(),
     }
 }
 
-pub fn idx_decr(i: &mut isize, delta: isize){
+pub fn idx_incr(i: &mut isize, delta: isize){
     if *i < 0 {
-        *i += delta;
-    }else {
         *i -= delta;
+    }else {
+        *i += delta;
     }
 }
 
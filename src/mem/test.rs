@@ -10,3 +10,53 @@ fn read_ltl(){
     m.nmem_allc(&[0f64; 2]);
     assert_eq!(m.read_ltl(-1).unwrap(), "asdasd");
 }
+
+#[test]
+fn heap_alloc_free(){
+    let mut m = Mem::new();
+    let a = m.heap_alloc(3).unwrap();
+    let b = m.heap_alloc(2).unwrap();
+    // header before each block holds its size
+    assert_eq!((a, b), (5, 9));
+    assert_eq!(m.mem_at(4).unwrap(), 3.0);
+    assert_eq!(m.pmem_len(), 11);
+    m.mem_set(a as isize, 5.0).unwrap();
+    m.heap_free(a as isize).unwrap();
+    assert_matches!(m.heap_free(a as isize), Err(Error::DoubleFree(5)));
+    assert_matches!(m.heap_free(6), Err(Error::NotHeapPtr(6)));
+    assert_matches!(m.heap_free(-1), Err(Error::NotHeapPtr(-1)));
+    // freed block is reused and zeroed, rest is split off
+    let c = m.heap_alloc(1).unwrap();
+    assert_eq!(c, a);
+    assert_eq!(m.mem_at(c as isize).unwrap(), 0.0);
+    assert_eq!(m.mem_at(4).unwrap(), 1.0);
+    assert_eq!(m.mem_at(6).unwrap(), 1.0);
+    // free at the end of pmem gives memory back
+    m.heap_free(b as isize).unwrap();
+    m.heap_free(c as isize).unwrap();
+    assert_eq!(m.pmem_len(), 4);
+}
+
+#[test]
+fn heap_realloc(){
+    let mut m = Mem::new();
+    let a = m.heap_alloc(2).unwrap();
+    m.mem_set(a as isize, 1.0).unwrap();
+    // last block grows in place
+    assert_eq!(m.heap_realloc(a as isize, 4).unwrap(), a);
+    assert_eq!(m.pmem_len(), a + 4);
+    let b = m.heap_alloc(1).unwrap();
+    // blocked by b, so it moves
+    let c = m.heap_realloc(a as isize, 6).unwrap();
+    assert_ne!(c, a);
+    assert_eq!(m.mem_at(c as isize).unwrap(), 1.0);
+    assert_eq!(m.mem_at(c as isize + 5).unwrap(), 0.0);
+    assert_matches!(m.heap_realloc(a as isize, 1), Err(Error::DoubleFree(_)));
+    // shrink keeps base
+    assert_eq!(m.heap_realloc(c as isize, 2).unwrap(), c);
+    assert_eq!(m.mem_at(c as isize - 1).unwrap(), 2.0);
+    // grow into freed block after it
+    m.heap_free(b as isize).unwrap();
+    let d = m.heap_alloc(1).unwrap();
+    assert_eq!(m.heap_realloc(d as isize, 5).unwrap(), d);
+}
@@ -0,0 +1,70 @@
+use crate::mem::Mem;
+use crate::code::Arg;
+use super::*;
+
+enum Type{
+    And, Or, Xor, Shl, Shr, Sar
+}
+
+// Operands are taken as 64-bit two's complement integers.
+// Results beyond 2^53 can not be stored exactly in a slot,
+// they are IntegerOverflow in strict mode
+fn binary_bit(t: Type, v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    argc_guard!(v, 2);
+    let left = v[0].get_int(m).arg(0)?;
+    let result = match t {
+        Type::And => (left & v[1].get_int(m).arg(1)?) as f64,
+        Type::Or => (left | v[1].get_int(m).arg(1)?) as f64,
+        Type::Xor => (left ^ v[1].get_int(m).arg(1)?) as f64,
+        // shifting out every bit gives 0, or -1 for negative sar
+        Type::Shl => left.checked_shl(shift(&v[1], m).arg(1)?).unwrap_or(0) as f64,
+        Type::Shr => (left as u64).checked_shr(shift(&v[1], m).arg(1)?).unwrap_or(0) as f64,
+        Type::Sar => left.checked_shr(shift(&v[1], m).arg(1)?)
+            .unwrap_or(if left < 0 { -1 } else { 0 }) as f64,
+    };
+    let result = check_num(m, &[], result, true)?;
+    m.mem_set(0, result).unwrap();
+    Ok(Signal::None)
+}
+
+fn shift(a: &Arg, m: &Mem) -> Result<u32, Error>{
+    let n = a.get_uint(m)?;
+    Ok(n.min(u32::MAX as u64) as u32)
+}
+
+pub fn band(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    binary_bit(Type::And, v, m)
+}
+
+pub fn bor(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    binary_bit(Type::Or, v, m)
+}
+
+pub fn bxor(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    binary_bit(Type::Xor, v, m)
+}
+
+pub fn bnot(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    argc_guard!(v, 1);
+    let value = v[0].get_int(m).arg(0)?;
+    m.mem_set(0, !value as f64).unwrap();
+    Ok(Signal::None)
+}
+
+// shift left
+pub fn shl(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    binary_bit(Type::Shl, v, m)
+}
+
+// logical shift right, fills with 0
+pub fn shr(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    binary_bit(Type::Shr, v, m)
+}
+
+// arithmetic shift right, fills with sign bit
+pub fn sar(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    binary_bit(Type::Sar, v, m)
+}
+
+#[cfg(test)]
+mod test;
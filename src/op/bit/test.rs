@@ -0,0 +1,75 @@
+use crate::code::Arg;
+use crate::mem::Mem;
+use crate::error::Error;
+use crate::op::Signal;
+
+fn run(f: fn(&[Arg], &mut Mem) -> Result<Signal, Error>, a: f64, b: f64) -> f64{
+    let v = vec![Arg::Num(a), Arg::Num(b)];
+    let mut m = Mem::new();
+    f(&v, &mut m).unwrap();
+    m.mem_at(0).unwrap()
+}
+
+#[test]
+fn band_bor_bxor(){
+    assert_eq!(run(super::band, 12.0, 10.0), 8.0);
+    assert_eq!(run(super::bor, 12.0, 10.0), 14.0);
+    assert_eq!(run(super::bxor, 12.0, 10.0), 6.0);
+    assert_eq!(run(super::band, -1.0, 255.0), 255.0);
+}
+
+#[test]
+fn bnot(){
+    let v = vec![Arg::Num(5.0)];
+    let mut m = Mem::new();
+    super::bnot(&v, &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), -6.0);
+}
+
+#[test]
+fn shift(){
+    assert_eq!(run(super::shl, 3.0, 4.0), 48.0);
+    assert_eq!(run(super::shl, 1.0, 64.0), 0.0);
+    assert_eq!(run(super::shr, 48.0, 4.0), 3.0);
+    assert_eq!(run(super::sar, 48.0, 4.0), 3.0);
+    assert_eq!(run(super::sar, -16.0, 2.0), -4.0);
+    assert_eq!(run(super::sar, -16.0, 100.0), -1.0);
+    // logical shift treats negative as 64-bit unsigned
+    assert_eq!(run(super::shr, -1.0, 60.0), 15.0);
+}
+
+#[test]
+fn not_integer(){
+    let mut m = Mem::new();
+    let v = vec![Arg::Num(1.5), Arg::Num(1.0)];
+    assert_matches!(super::band(&v, &mut m),
+        Err(Error::Arg(0, ref e)) if matches!(**e, Error::NotInterger(_)));
+    let v = vec![Arg::Num(1.0), Arg::Num(0.5)];
+    assert_matches!(super::bor(&v, &mut m),
+        Err(Error::Arg(1, ref e)) if matches!(**e, Error::NotInterger(_)));
+    let v = vec![Arg::Num(f64::NAN)];
+    assert_matches!(super::bnot(&v, &mut m),
+        Err(Error::Arg(0, ref e)) if matches!(**e, Error::NotInterger(_)));
+    // shift amount must not be negative
+    let v = vec![Arg::Num(1.0), Arg::Num(-1.0)];
+    assert_matches!(super::shl(&v, &mut m),
+        Err(Error::Arg(1, ref e)) if matches!(**e, Error::NegativeOrNotInterger(_)));
+}
+
+#[test]
+fn argc(){
+    let v = vec![Arg::Num(0.0)];
+    let mut m = Mem::new();
+    assert_matches!(super::bxor(&v, &mut m), Err(Error::WrongArgCount(2, 1)));
+}
+
+#[test]
+fn strict_overflow(){
+    let v = vec![Arg::Num(1.0), Arg::Num(53.0)];
+    let mut m = Mem::new();
+    super::shl(&v, &mut m).unwrap();
+    m.set_strict(true);
+    assert_matches!(super::shl(&v, &mut m), Err(Error::IntegerOverflow(_)));
+    let v = vec![Arg::Num(1.0), Arg::Num(52.0)];
+    super::shl(&v, &mut m).unwrap();
+}
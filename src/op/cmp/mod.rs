@@ -1,11 +1,11 @@
 use crate::mem::Mem;
-use crate::lex::Tok;
+use crate::code::Arg;
 use super::*;
 
-fn parse_arg(v: &[Tok], m: &Mem) -> Result<(f64, f64), Error>{
+fn parse_arg(v: &[Arg], m: &Mem) -> Result<(f64, f64), Error>{
     argc_guard!(v, 2);
-    Ok((v[0].get_value(m)?, 
-        v[1].get_value(m)?))
+    Ok((v[0].get_value(m).arg(0)?, 
+        v[1].get_value(m).arg(1)?))
 }
 
 macro_rules! cmp {
@@ -19,21 +19,53 @@ macro_rules! cmp {
     }
 }
 
-pub fn eq(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn eq(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     cmp!(==, v, m)
 }
 
-pub fn ne(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn ne(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     cmp!(!=, v, m)
 }
 
-pub fn gt(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn gt(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     cmp!(>, v, m)
 }
 
-pub fn lt(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn lt(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     cmp!(<, v, m)
 }
 
+pub fn ge(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    cmp!(>=, v, m)
+}
+
+pub fn le(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    cmp!(<=, v, m)
+}
+
+// [0] is set to -1, 0 or 1 for less, equal or greater.
+// NaN if either side is NaN, as they are unordered
+pub fn cmp(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    let (left, right) = parse_arg(v, m)?;
+    let result = match left.partial_cmp(&right) {
+        Some(o) => o as i8 as f64,
+        None => f64::NAN,
+    };
+    m.mem_set(0, result).unwrap();
+    Ok(Signal::None)
+}
+
+// approximately equal: |left - right| <= epsilon.
+// Equal infinities are equal, NaN is never equal
+pub fn aeq(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    argc_guard!(v, 3);
+    let left = v[0].get_value(m).arg(0)?;
+    let right = v[1].get_value(m).arg(1)?;
+    let epsilon = v[2].get_value(m).arg(2)?;
+    let result = (left == right || (left - right).abs() <= epsilon) as i8 as f64;
+    m.mem_set(0, result).unwrap();
+    Ok(Signal::None)
+}
+
 #[cfg(test)]
 mod test;
@@ -1,9 +1,9 @@
-use crate::lex::Tok;
+use crate::code::Arg;
 use crate::mem::Mem;
 
 #[test]
 fn eq(){
-    let v = vec![Tok::Num(1.0), Tok::Num(1.0)];
+    let v = vec![Arg::Num(1.0), Arg::Num(1.0)];
     let mut m = Mem::new();
     super::eq(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 1.0);
@@ -11,7 +11,7 @@ fn eq(){
 
 #[test]
 fn ne(){
-    let v = vec![Tok::Num(1.0), Tok::Num(1.0)];
+    let v = vec![Arg::Num(1.0), Arg::Num(1.0)];
     let mut m = Mem::new();
     super::ne(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 0.0);
@@ -19,23 +19,82 @@ fn ne(){
 
 #[test]
 fn gt(){
-    let v = vec![Tok::Num(1.0), Tok::Num(1.0)];
+    let v = vec![Arg::Num(1.0), Arg::Num(1.0)];
     let mut m = Mem::new();
     super::gt(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 0.0);
-    let v = vec![Tok::Num(2.0), Tok::Num(1.0)];
+    let v = vec![Arg::Num(2.0), Arg::Num(1.0)];
     super::gt(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 1.0);
 }
 
 #[test]
 fn lt(){
-    let v = vec![Tok::Num(1.0), Tok::Num(1.0)];
+    let v = vec![Arg::Num(1.0), Arg::Num(1.0)];
     let mut m = Mem::new();
     super::lt(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 0.0);
 
-    let v = vec![Tok::Num(0.0), Tok::Num(1.0)];
+    let v = vec![Arg::Num(0.0), Arg::Num(1.0)];
     super::lt(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 1.0);
 }
+
+#[test]
+fn ge_le(){
+    let mut m = Mem::new();
+    for (l, r, ge, le) in &[(1.0, 1.0, 1.0, 1.0), (2.0, 1.0, 1.0, 0.0), (0.0, 1.0, 0.0, 1.0)] {
+        let v = vec![Arg::Num(*l), Arg::Num(*r)];
+        super::ge(&v, &mut m).unwrap();
+        assert_eq!(m.mem_at(0).unwrap(), *ge);
+        super::le(&v, &mut m).unwrap();
+        assert_eq!(m.mem_at(0).unwrap(), *le);
+    }
+}
+
+#[test]
+fn cmp(){
+    let mut m = Mem::new();
+    for (l, r, o) in &[(1.0, 2.0, -1.0), (2.0, 2.0, 0.0), (3.0, 2.0, 1.0), (f64::NEG_INFINITY, -1e300, -1.0)] {
+        let v = vec![Arg::Num(*l), Arg::Num(*r)];
+        super::cmp(&v, &mut m).unwrap();
+        assert_eq!(m.mem_at(0).unwrap(), *o);
+    }
+}
+
+#[test]
+fn aeq(){
+    let mut m = Mem::new();
+    let v = vec![Arg::Num(0.1 + 0.2), Arg::Num(0.3), Arg::Num(1e-9)];
+    super::aeq(&v, &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 1.0);
+    let v = vec![Arg::Num(1.0), Arg::Num(1.1), Arg::Num(0.05)];
+    super::aeq(&v, &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 0.0);
+    let v = vec![Arg::Num(f64::INFINITY), Arg::Num(f64::INFINITY), Arg::Num(0.0)];
+    super::aeq(&v, &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 1.0);
+    let v = vec![Arg::Num(1.0), Arg::Num(1.0)];
+    assert_matches!(super::aeq(&v, &mut m), Err(crate::Error::WrongArgCount(3, 2)));
+}
+
+#[test]
+fn nan(){
+    let mut m = Mem::new();
+    let v = vec![Arg::Num(f64::NAN), Arg::Num(f64::NAN)];
+    type Op = fn(&[Arg], &mut Mem) -> Result<crate::op::Signal, crate::Error>;
+    let ops: &[(Op, f64)] = &[
+        (super::eq, 0.0), (super::ne, 1.0), (super::gt, 0.0),
+        (super::lt, 0.0), (super::ge, 0.0), (super::le, 0.0),
+    ];
+    for (op, expect) in ops {
+        op(&v, &mut m).unwrap();
+        assert_eq!(m.mem_at(0).unwrap(), *expect);
+    }
+    let v = vec![Arg::Num(1.0), Arg::Num(f64::NAN)];
+    super::cmp(&v, &mut m).unwrap();
+    assert!(m.mem_at(0).unwrap().is_nan());
+    let v = vec![Arg::Num(f64::NAN), Arg::Num(f64::NAN), Arg::Num(f64::INFINITY)];
+    super::aeq(&v, &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 0.0);
+}
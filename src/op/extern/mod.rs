@@ -1,11 +1,11 @@
 use crate::mem::Mem;
-use crate::lex::Tok;
+use crate::code::Arg;
 use super::*;
 
 // source another file, load labels and symbols, don't execute
 //      src: script_name(Sym)
-pub fn src(v: &[Tok], _: &mut Mem) -> Result<Signal, Error>{
+pub fn src(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 1);
-    let name = v[0].get_sym()?;
-    Ok(Signal::Src(name.sym.clone()))
+    let name = v[0].get_sym().arg(0)?;
+    Ok(Signal::Src(m.sym_at(name).to_owned()))
 }
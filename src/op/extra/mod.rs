@@ -1,28 +1,278 @@
 use crate::error::Error;
-use crate::lex::Tok;
-use crate::mem::Mem;
+use crate::code::Arg;
+use crate::mem::{Mem, idx_incr};
 use super::*;
-use std::{
-    fs::File,
-    os::unix::io::{ FromRawFd, IntoRawFd },
-    io::Write,
-};
 
 // Write formatted value to fd
 //      print_num: fd(Value, val(Value)
-pub fn print_num(v: &[Tok], m: &mut Mem) -> Result<Signal, Error> {
+pub fn print_num(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
     argc_guard!(v, 2);
-    let fd = v[0].get_uint(m)? as i32;
+    let fd = v[0].get_uint(m).arg(0)? as i32;
     // check if fd is opened
-    if !m.fd[fd as usize] {
-        return Err(Error::BadFileDescriptor(fd));
-    }
-    let val = v[1].get_value(m)?;
-    // open file
-    let mut f = unsafe { File::from_raw_fd(fd) };
+    m.io_writer(fd).arg(0)?;
+    let val = v[1].get_value(m).arg(1)?;
     // fmt float to string and write to fd
-    f.write_fmt(format_args!("{}", val)).unwrap();
-    // return file ownership to fd
-    f.into_raw_fd();
+    if let Err(e) = m.io_writer(fd)?.write_fmt(format_args!("{}", val)) {
+        return Err(Error::IoError(e));
+    }
+    Ok(Signal::None)
+}
+
+// Write zero-terminated string to fd, from either nmem or pmem
+//      print_str: fd(Value), ptr(Ptr)
+pub fn print_str(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
+    argc_guard!(v, 2);
+    let s = v[1].get_loc(m).and_then(|p| m.ltl_bytes(p)).arg(1)?;
+    write_fd(v[0].get_uint(m).arg(0)? as i32, m, &s).arg(0)
+}
+
+// print_str followed by newline
+//      println: fd(Value), ptr(Ptr)
+pub fn println(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
+    argc_guard!(v, 2);
+    let mut s = v[1].get_loc(m).and_then(|p| m.ltl_bytes(p)).arg(1)?;
+    s.push(b'\n');
+    write_fd(v[0].get_uint(m).arg(0)? as i32, m, &s).arg(0)
+}
+
+// [0] set to length of zero-terminated string
+//      strlen: ptr(Ptr)
+pub fn strlen(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
+    argc_guard!(v, 1);
+    let len = v[0].get_loc(m).and_then(|p| m.ltl_bytes(p)).arg(0)?.len();
+    m.mem_set(0, len as f64)?;
+    Ok(Signal::None)
+}
+
+// Write args formatted by fmt to fd.
+// Conversions are %[flags][width][.precision]conv, %% for %
+//   flags: - left align, 0 pad numbers with 0, + sign on positive numbers
+//   conv: d integer, f float with precision (6 by default),
+//         c character, x X hexadecimal, s string at ptr
+//      printf: fd(Value), fmt(Fmt), args..
+pub fn printf(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
+    if v.len() < 2 {
+        return Err(Error::WrongArgCount(2, v.len()));
+    }
+    let pieces = v[1].get_loc(m)
+        .and_then(|p| m.ltl_bytes(p))
+        .and_then(|f| parse_fmt(&f))
+        .arg(1)?;
+    let convs = pieces.iter().filter(|p| matches!(p, Piece::Conv(_))).count();
+    argc_guard!(v, 2 + convs);
+    let mut s = Vec::new();
+    let mut args = v.iter().enumerate().skip(2);
+    for p in &pieces {
+        match p {
+            Piece::Text(t) => s.extend_from_slice(t),
+            // args count is checked above
+            Piece::Conv(c) => {
+                let (pos, a) = args.next().unwrap();
+                s.extend(c.format(a, m).arg(pos)?);
+            },
+        }
+    }
+    write_fd(v[0].get_uint(m).arg(0)? as i32, m, &s).arg(0)
+}
+
+// Part of printf format
+#[derive(Clone, PartialEq, Debug)]
+pub enum Piece{
+    Text(Vec<u8>),
+    Conv(Conv),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Conv{
+    left: bool,
+    zero: bool,
+    plus: bool,
+    width: usize,
+    prec: Option<usize>,
+    conv: u8,
+}
+
+impl Conv{
+    // kind of arg taken by conversion
+    pub fn kind(&self) -> ArgKind {
+        if self.conv == b's' {
+            ArgKind::Ptr
+        }else{
+            ArgKind::Value
+        }
+    }
+
+    fn format(&self, a: &Arg, m: &Mem) -> Result<Vec<u8>, Error> {
+        let (body, numeric) = match self.conv {
+            b'd' => (self.sign(a.get_int(m)?.to_string()), true),
+            b'f' => {
+                let f = a.get_value(m)?;
+                (self.sign(format!("{:.*}", self.prec.unwrap_or(6), f)), f.is_finite())
+            },
+            b'x' => (format!("{:x}", a.get_int(m)?), true),
+            b'X' => (format!("{:X}", a.get_int(m)?), true),
+            b'c' => return Ok(self.pad(vec![a.get_uint(m)? as u8], false)),
+            _ => {
+                let mut s = m.ltl_bytes(a.get_loc(m)?)?;
+                if let Some(p) = self.prec {
+                    s.truncate(p);
+                }
+                return Ok(self.pad(s, false));
+            },
+        };
+        Ok(self.pad(body.into_bytes(), numeric))
+    }
+
+    fn sign(&self, s: String) -> String {
+        if self.plus && !s.starts_with('-') {
+            format!("+{}", s)
+        }else{
+            s
+        }
+    }
+
+    // pad to width, zeros go after sign
+    fn pad(&self, mut s: Vec<u8>, numeric: bool) -> Vec<u8> {
+        if s.len() >= self.width {
+            return s;
+        }
+        let n = self.width - s.len();
+        if self.left {
+            s.resize(self.width, b' ');
+        }else if self.zero && numeric {
+            let at = if s[0] == b'-' || s[0] == b'+' { 1 } else { 0 };
+            s.splice(at..at, std::iter::repeat_n(b'0', n));
+        }else{
+            s.splice(0..0, std::iter::repeat_n(b' ', n));
+        }
+        s
+    }
+}
+
+// Split printf format into text and conversions
+pub fn parse_fmt(f: &[u8]) -> Result<Vec<Piece>, Error> {
+    let mut pieces = Vec::new();
+    let mut text = Vec::new();
+    let mut i = 0;
+    while i < f.len() {
+        if f[i] != b'%' {
+            text.push(f[i]);
+            i += 1;
+            continue;
+        }
+        i += 1;
+        if f.get(i) == Some(&b'%') {
+            text.push(b'%');
+            i += 1;
+            continue;
+        }
+        let mut c = Conv { left: false, zero: false, plus: false, width: 0, prec: None, conv: 0 };
+        while let Some(flag) = f.get(i) {
+            match flag {
+                b'-' => c.left = true,
+                b'0' => c.zero = true,
+                b'+' => c.plus = true,
+                _ => break,
+            }
+            i += 1;
+        }
+        c.width = parse_digits(f, &mut i)?.unwrap_or(0);
+        if f.get(i) == Some(&b'.') {
+            i += 1;
+            c.prec = Some(parse_digits(f, &mut i)?.unwrap_or(0));
+        }
+        c.conv = match f.get(i) {
+            Some(b'd') | Some(b'i') => b'd',
+            Some(&conv @ b'f') | Some(&conv @ b'c') | Some(&conv @ b'x') |
+            Some(&conv @ b'X') | Some(&conv @ b's') => conv,
+            Some(conv) =>
+                return Err(Error::InvalidFormat(format!("unknown conversion %{}", *conv as char))),
+            None =>
+                return Err(Error::InvalidFormat("incomplete conversion at end".to_owned())),
+        };
+        i += 1;
+        if !text.is_empty() {
+            pieces.push(Piece::Text(std::mem::take(&mut text)));
+        }
+        pieces.push(Piece::Conv(c));
+    }
+    if !text.is_empty() {
+        pieces.push(Piece::Text(text));
+    }
+    Ok(pieces)
+}
+
+fn parse_digits(f: &[u8], i: &mut usize) -> Result<Option<usize>, Error> {
+    let start = *i;
+    while f.get(*i).is_some_and(|c| c.is_ascii_digit()) {
+        *i += 1;
+    }
+    if start == *i {
+        return Ok(None);
+    }
+    // digits are ascii
+    let s = std::str::from_utf8(&f[start..*i]).unwrap();
+    match s.parse::<usize>() {
+        Ok(n) => Ok(Some(n)),
+        Err(_) => Err(Error::InvalidFormat(format!("width or precision too large: {}", s))),
+    }
+}
+
+// Parse string at ptr as number. String ends at 0, like the ones from read.
+// [0] set to the number, [1] to 0 or to Error::code of ParseNumError
+//      parse_num: ptr(Ptr)
+pub fn parse_num(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
+    argc_guard!(v, 1);
+    let s = v[0].get_loc(m).and_then(|p| num_bytes(m, p)).arg(0)?;
+    store_num(m, &s, false)
+}
+
+// parse_num that also sets [1] to Error::code of NotInterger if number is not an integer
+//      parse_int: ptr(Ptr)
+pub fn parse_int(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
+    argc_guard!(v, 1);
+    let s = v[0].get_loc(m).and_then(|p| num_bytes(m, p)).arg(0)?;
+    store_num(m, &s, true)
+}
+
+fn num_bytes(m: &Mem, mut i: isize) -> Result<Vec<u8>, Error> {
+    let mut s = Vec::new();
+    loop {
+        let c = m.mem_at(i)?;
+        if c == 0.0 {
+            return Ok(s);
+        }
+        s.push(c as u8);
+        idx_incr(&mut i, 1);
+    }
+}
+
+// Parse token surrounded by whitespace and store it to [0].
+// [1] set to 0, or to Error::code if token is not a number.
+// [0] set to 0 on failure
+pub fn store_num(m: &mut Mem, tok: &[u8], int: bool) -> Result<Signal, Error> {
+    let r = match String::from_utf8_lossy(tok).trim().parse::<f64>() {
+        Ok(f) if int && f.fract() != 0.0 => Err(Error::NotInterger(f)),
+        Ok(f) => Ok(f),
+        Err(e) => Err(Error::ParseNumError(e)),
+    };
+    match r {
+        Ok(f) => {
+            m.mem_set(0, f)?;
+            m.mem_set(1, 0.0)?;
+        },
+        Err(e) => {
+            m.mem_set(0, 0.0)?;
+            m.mem_set(1, e.code() as f64)?;
+        },
+    }
+    Ok(Signal::None)
+}
+
+fn write_fd(fd: i32, m: &mut Mem, s: &[u8]) -> Result<Signal, Error> {
+    m.io_writer(fd)?.write_all(s).map_err(Error::IoError)?;
     Ok(Signal::None)
 }
+
+#[cfg(test)]
+mod test;
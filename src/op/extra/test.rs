@@ -0,0 +1,99 @@
+use crate::code::Arg;
+use crate::mem::Mem;
+use crate::error::Error;
+use crate::stream::{Stream, Capture};
+
+// mem with fd 3 writing to returned buffer
+fn capture() -> (Mem, Capture){
+    let mut m = Mem::new();
+    let out = Capture::default();
+    m.io_attach(3, Stream::Out(Box::new(out.clone())));
+    (m, out)
+}
+
+#[test]
+fn strlen(){
+    let mut m = Mem::new();
+    let s = m.ltl_intern("hello");
+    super::strlen(&[Arg::Ltl(s)], &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 5.0);
+    // single 0 is part of string
+    m.pmem_allc(&[104.0, 0.0, 105.0, 0.0, 0.0]);
+    super::strlen(&[Arg::Idx(4)], &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 3.0);
+    // unterminated string runs out of pmem
+    m.pmem_allc(&[1.0]);
+    assert_matches!(super::strlen(&[Arg::Idx(9)], &mut m),
+        Err(Error::Arg(0, ref e)) if matches!(**e, Error::InvalidMemAccess(10)));
+}
+
+#[test]
+fn print_str(){
+    let (mut m, out) = capture();
+    let fd = 3.0;
+    let s = m.ltl_intern("hi");
+    m.pmem_allc(&[111.0, 107.0, 0.0, 0.0]);
+    super::print_str(&[Arg::Num(fd), Arg::Ltl(s)], &mut m).unwrap();
+    super::println(&[Arg::Num(fd), Arg::Idx(4)], &mut m).unwrap();
+    assert_eq!(out.to_string_lossy(), "hiok\n");
+}
+
+#[test]
+fn print_str_bad_fd(){
+    let mut m = Mem::new();
+    let s = m.ltl_intern("hi");
+    assert_matches!(super::print_str(&[Arg::Num(100.0), Arg::Ltl(s)], &mut m),
+        Err(Error::Arg(0, ref e)) if matches!(**e, Error::BadFileDescriptor(100)));
+}
+
+#[test]
+fn parse_fmt(){
+    use super::Piece;
+    let p = super::parse_fmt(b"a%%b%-5.2fc").unwrap();
+    assert_eq!(p.len(), 3);
+    assert_eq!(p[0], Piece::Text(b"a%b".to_vec()));
+    assert_matches!(p[1], Piece::Conv(_));
+    assert_eq!(p[2], Piece::Text(b"c".to_vec()));
+    assert_matches!(super::parse_fmt(b"%"), Err(Error::InvalidFormat(_)));
+    assert_matches!(super::parse_fmt(b"%5"), Err(Error::InvalidFormat(_)));
+    assert_matches!(super::parse_fmt(b"%y"), Err(Error::InvalidFormat(_)));
+}
+
+#[test]
+fn printf(){
+    let (mut m, out) = capture();
+    let fd = 3.0;
+    let fmt = m.ltl_intern("%d|%5d|%-5d|%05d|%+d|%.2f|%08.3f|%c|%x|%X|%-4s|%.1s\n");
+    let s = m.ltl_intern("ab");
+    let v = vec![
+        Arg::Num(fd), Arg::Ltl(fmt),
+        Arg::Num(-3.0), Arg::Num(42.0), Arg::Num(42.0), Arg::Num(-42.0), Arg::Num(7.0),
+        Arg::Num(1.23456), Arg::Num(-2.5), Arg::Num(65.0), Arg::Num(255.0), Arg::Num(255.0),
+        Arg::Ltl(s), Arg::Ltl(s),
+    ];
+    super::printf(&v, &mut m).unwrap();
+    // wrong count and non-integer are runtime errors too
+    assert_matches!(super::printf(&v[..3], &mut m), Err(Error::WrongArgCount(14, 3)));
+    let fmt = m.ltl_intern("%d");
+    assert_matches!(super::printf(&[Arg::Num(fd), Arg::Ltl(fmt), Arg::Num(1.5)], &mut m),
+        Err(Error::Arg(2, ref e)) if matches!(**e, Error::NotInterger(_)));
+    assert_eq!(out.to_string_lossy(),
+        "-3|   42|42   |-0042|+7|1.23|-002.500|A|ff|FF|ab  |a\n");
+}
+
+#[test]
+fn parse_num(){
+    let mut m = Mem::new();
+    // as left by read: "-1.5\n" then 0
+    m.pmem_allc(&[45.0, 49.0, 46.0, 53.0, 10.0, 0.0, 55.0]);
+    super::parse_num(&[Arg::Idx(4)], &mut m).unwrap();
+    assert_eq!((m.mem_at(0).unwrap(), m.mem_at(1).unwrap()), (-1.5, 0.0));
+    super::parse_int(&[Arg::Idx(4)], &mut m).unwrap();
+    assert_eq!((m.mem_at(0).unwrap(), m.mem_at(1).unwrap()), (0.0, -21.0));
+    let s = m.ltl_intern("1e3");
+    super::parse_int(&[Arg::Ltl(s)], &mut m).unwrap();
+    assert_eq!((m.mem_at(0).unwrap(), m.mem_at(1).unwrap()), (1000.0, 0.0));
+    let s = m.ltl_intern("x1");
+    super::parse_num(&[Arg::Ltl(s)], &mut m).unwrap();
+    assert_eq!((m.mem_at(0).unwrap(), m.mem_at(1).unwrap()), (0.0, -2.0));
+}
@@ -1,23 +1,23 @@
 use super::*;
-use crate::lex::Tok;
+use crate::code::Arg;
 use crate::mem::Mem;
 
 // unconditional jump
 //      jmp: lbl(Sym)
-pub fn jmp(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn jmp(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 1);
-    let label = v[0].get_sym()?;
+    let label = v[0].get_lbl().arg(0)?;
     let loc = m.label_find(label)?;
     Ok(Signal::Jmp(loc))
 }
 
 // jump if cond is true
 //      jc: cond(Value), lbl(Sym)
-pub fn jc(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn jc(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 2);
-    let cond = v[0].get_value(m)?;
+    let cond = v[0].get_value(m).arg(0)?;
     if cond != 0.0 {
-        let label = v[1].get_sym()?;
+        let label = v[1].get_lbl().arg(1)?;
         let loc = m.label_find(label)?;
         Ok(Signal::Jmp(loc))
     }else{
@@ -29,21 +29,51 @@ pub fn jc(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
 // Label symbol created during preprocess
 // This function updates the line number the label points to
 //      lbl: lbl(Sym)
-pub fn lbl(v: &[Tok], _: &mut Mem) -> Result<Signal, Error>{
+pub fn lbl(v: &[Arg], _: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 1);
-    let label = v[0].get_sym()?;
-    Ok(Signal::SetLbl(label.idx))
+    let label = v[0].get_lbl().arg(0)?;
+    Ok(Signal::SetLbl(label))
 }
 
 // Set alias.
 // Act as function pointer
 // Allowing changing label during run time to know label locations
-pub fn als(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn als(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 2);
-    let alias = v[0].get_sym()?;
-    let label = v[1].get_sym()?;
+    let alias = v[0].get_lbl().arg(0)?;
+    let label = v[1].get_lbl().arg(1)?;
     let loc = m.label_find(label)?;
-    Ok(Signal::SetAls(alias.idx, loc))
+    Ok(Signal::SetAls(alias, loc))
+}
+
+// Call subroutine.
+// Line after call is pushed to the return stack
+//      call: lbl(Sym)
+pub fn call(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    argc_guard!(v, 1);
+    let label = v[0].get_lbl().arg(0)?;
+    let loc = m.label_find(label)?;
+    Ok(Signal::Call(loc))
+}
+
+// Return to the line popped from the return stack
+//      ret
+pub fn ret(v: &[Arg], _: &mut Mem) -> Result<Signal, Error>{
+    argc_guard!(v, 0);
+    Ok(Signal::Ret)
+}
+
+// Register error handler in the current file.
+// On runtime error, including src failing to load, [0] is set to payload,
+// [1] to error code, [2] to line and [3] to file name of the error, then jump to lbl.
+// Files sourced after trap are dropped.
+// Handler is cleared once used, trap again to re-arm
+//      trap: lbl(Sym)
+pub fn trap(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    argc_guard!(v, 1);
+    let label = v[0].get_lbl().arg(0)?;
+    m.label_find(label)?;
+    Ok(Signal::Trap(label))
 }
 
 #[cfg(test)]
@@ -1,17 +1,12 @@
-use crate::lex::*;
+use crate::code::Arg;
 use crate::mem::Mem;
 use crate::code::Code;
 use super::*;
 
 #[test]
 fn jmp(){
-    let mut code = Code::new();
     let mut mem = Mem::new();
-    let v1 = vec![
-        Tok::Sym(HashIdx::new("L", 0))];
-    let v2 = vec![Tok::Num(10.0), Tok::Num(10.0)];
-    code.push(v1.clone());
-    code.push(v2.clone());
+    let v1 = vec![Arg::Lbl(0)];
     mem.label_add(0);
     let l = if let Signal::Jmp(l) = super::jmp(&v1, &mut mem).unwrap() {
         l
@@ -20,3 +15,47 @@ fn jmp(){
     };
     assert_eq!(l, 0);
 }
+
+#[test]
+fn call_ret(){
+    let mut mem = Mem::new();
+    let mut code = Code::new();
+    let v = vec![Arg::Lbl(0)];
+    mem.label_add(5);
+    let s = super::call(&v, &mut mem).unwrap();
+    assert_eq!(s, Signal::Call(5));
+    code.ptr_set(2);
+    s.respond(&mut mem, &mut code).unwrap();
+    assert_eq!(code.ptr(), 5);
+    let s = super::ret(&[], &mut mem).unwrap();
+    s.respond(&mut mem, &mut code).unwrap();
+    assert_eq!(code.ptr(), 3);
+}
+
+#[test]
+fn ret_without_call(){
+    let mut mem = Mem::new();
+    let mut code = Code::new();
+    let r = Signal::Ret.respond(&mut mem, &mut code);
+    assert_matches!(r, Err(Error::CallStackUnderflow));
+}
+
+#[test]
+fn call_depth_limit(){
+    let mut mem = Mem::new();
+    let mut code = Code::new();
+    mem.set_call_depth(2);
+    Signal::Call(0).respond(&mut mem, &mut code).unwrap();
+    Signal::Call(0).respond(&mut mem, &mut code).unwrap();
+    let r = Signal::Call(0).respond(&mut mem, &mut code);
+    assert_matches!(r, Err(Error::CallStackOverflow(2)));
+}
+
+#[test]
+fn trap(){
+    let mut mem = Mem::new();
+    mem.label_add(3);
+    assert_eq!(super::trap(&[Arg::Lbl(0)], &mut mem).unwrap(), Signal::Trap(0));
+    let r = super::trap(&[Arg::Lbl(1)], &mut mem);
+    assert_matches!(r, Err(crate::Error::UnknownLabel(_)));
+}
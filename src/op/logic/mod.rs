@@ -1,15 +1,15 @@
 use crate::mem::Mem;
-use crate::lex::Tok;
+use crate::code::Arg;
 use super::*;
 
 enum Type{
     And, Or
 }
 
-fn unary_logic(t: Type, v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+fn unary_logic(t: Type, v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 2);
-    let left = v[0].get_value(m)?;
-    let right = v[1].get_value(m)?;
+    let left = v[0].get_value(m).arg(0)?;
+    let right = v[1].get_value(m).arg(1)?;
     // compare if non zero
     let result = match t {
         Type::And => 
@@ -21,17 +21,17 @@ fn unary_logic(t: Type, v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
     Ok(Signal::None)
 }
 
-pub fn and(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn and(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     unary_logic(Type::And, v, m)
 }
 
-pub fn or(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn or(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     unary_logic(Type::Or, v, m)
 }
 
-pub fn not(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn not(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 1);
-    let value = v[0].get_value(m)?;
+    let value = v[0].get_value(m).arg(0)?;
     // compare if non zero
     let result = (value == 0.0) as i64 as f64;
     m.mem_set(0, result).unwrap();
@@ -1,10 +1,10 @@
-use crate::lex::Tok;
+use crate::code::Arg;
 use crate::mem::Mem;
 use crate::error::Error;
 
 #[test]
 fn and(){
-    let v = vec![Tok::Num(1.0), Tok::Num(10.0)];
+    let v = vec![Arg::Num(1.0), Arg::Num(10.0)];
     let mut m = Mem::new();
     super::and(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 1.0);
@@ -12,7 +12,7 @@ fn and(){
 
 #[test]
 fn and_false(){
-    let v = vec![Tok::Num(0.0), Tok::Num(10.0)];
+    let v = vec![Arg::Num(0.0), Arg::Num(10.0)];
     let mut m = Mem::new();
     super::and(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 0.0);
@@ -20,18 +20,18 @@ fn and_false(){
 
 #[test]
 fn or(){
-    let v = vec![Tok::Num(1.0), Tok::Num(10.0)];
+    let v = vec![Arg::Num(1.0), Arg::Num(10.0)];
     let mut m = Mem::new();
     super::or(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 1.0);
-    let v = vec![Tok::Num(0.0), Tok::Num(10.0)];
+    let v = vec![Arg::Num(0.0), Arg::Num(10.0)];
     super::or(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 1.0);
 }
 
 #[test]
 fn or_false(){
-    let v = vec![Tok::Num(0.0), Tok::Num(0.0)];
+    let v = vec![Arg::Num(0.0), Arg::Num(0.0)];
     let mut m = Mem::new();
     super::and(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 0.0);
@@ -39,7 +39,7 @@ fn or_false(){
 
 #[test]
 fn and_or_argc(){
-    let v = vec![Tok::Num(0.0)];
+    let v = vec![Arg::Num(0.0)];
     let mut m = Mem::new();
     let r = super::and(&v, &mut m).unwrap_err();
     assert_matches!(r, Error::WrongArgCount(2, 1));
@@ -47,7 +47,7 @@ fn and_or_argc(){
 
 #[test]
 fn not(){
-    let v = vec![Tok::Num(0.0)];
+    let v = vec![Arg::Num(0.0)];
     let mut m = Mem::new();
     super::not(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 1.0);
@@ -55,7 +55,7 @@ fn not(){
 
 #[test]
 fn not_false(){
-    let v = vec![Tok::Num(10.0)];
+    let v = vec![Arg::Num(10.0)];
     let mut m = Mem::new();
     super::not(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 0.0);
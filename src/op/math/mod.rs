@@ -1,11 +1,16 @@
 use crate::mem::Mem;
-use crate::lex::Tok;
+use crate::code::Arg;
 use super::*;
 
-fn parse_arg(v: &[Tok], m: &Mem) -> Result<(f64, f64), Error>{
+fn parse_arg(v: &[Arg], m: &Mem) -> Result<(f64, f64), Error>{
     argc_guard!(v, 2);
-    Ok((v[0].get_value(m)?, 
-        v[1].get_value(m)?))
+    Ok((v[0].get_value(m).arg(0)?, 
+        v[1].get_value(m).arg(1)?))
+}
+
+fn parse_unary_arg(v: &[Arg], m: &Mem) -> Result<f64, Error>{
+    argc_guard!(v, 1);
+    v[0].get_value(m).arg(0)
 }
 
 // [0] is set as result
@@ -13,31 +18,137 @@ macro_rules! math {
     ( $o:tt, $v:expr, $m:expr ) => {
         {
             let (left, right) = parse_arg($v, $m)?;
-            let result = left $o right;
+            let result = check_num($m, &[left, right], left $o right, true)?;
+            $m.mem_set(0, result).unwrap();
+            return Ok(Signal::None)
+        } 
+    };
+    // right side must not be 0 in strict mode
+    ( $o:tt, $v:expr, $m:expr, nonzero ) => {
+        {
+            let (left, right) = parse_arg($v, $m)?;
+            if $m.strict() && right == 0.0 {
+                return Err(Error::DivByZero.arg(1));
+            }
+            let result = check_num($m, &[left, right], left $o right, true)?;
             $m.mem_set(0, result).unwrap();
             return Ok(Signal::None)
         } 
-    }
+    };
 }
 
-pub fn add(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn add(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     math!(+, v, m)
 }
 
-pub fn sub(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn sub(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     math!(-, v, m)
 }
 
-pub fn mul(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn mul(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     math!(*, v, m)
 }
 
-pub fn div(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
-    math!(/ , v, m)
+pub fn div(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math!(/ , v, m, nonzero)
+}
+
+pub fn r#mod(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math!(% , v, m, nonzero)
+}
+
+// [0] is set as result of f64 method.
+// Out of domain input gives NaN, e.g. sqrt: -1
+macro_rules! math_fn {
+    ( $f:ident, $v:expr, $m:expr ) => {
+        {
+            let x = parse_unary_arg($v, $m)?;
+            // only operand is the cause
+            let result = check_num($m, &[x], x.$f(), false).arg(0)?;
+            $m.mem_set(0, result).unwrap();
+            return Ok(Signal::None)
+        }
+    };
+    ( $f:ident, $v:expr, $m:expr, binary ) => {
+        {
+            let (left, right) = parse_arg($v, $m)?;
+            let result = check_num($m, &[left, right], left.$f(right), false)?;
+            $m.mem_set(0, result).unwrap();
+            return Ok(Signal::None)
+        }
+    };
+}
+
+pub fn sqrt(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(sqrt, v, m)
+}
+
+// base to the power of exp
+pub fn pow(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    let (base, exp) = parse_arg(v, m)?;
+    let result = check_num(m, &[base, exp], base.powf(exp), true)?;
+    m.mem_set(0, result).unwrap();
+    Ok(Signal::None)
+}
+
+pub fn exp(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(exp, v, m)
+}
+
+pub fn ln(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(ln, v, m)
+}
+
+pub fn log10(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(log10, v, m)
+}
+
+// radians
+pub fn sin(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(sin, v, m)
+}
+
+pub fn cos(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(cos, v, m)
+}
+
+pub fn tan(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(tan, v, m)
+}
+
+// angle of point (x, y), args are y then x
+pub fn atan2(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(atan2, v, m, binary)
+}
+
+pub fn floor(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(floor, v, m)
+}
+
+pub fn ceil(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(ceil, v, m)
+}
+
+// half way cases round away from 0
+pub fn round(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(round, v, m)
+}
+
+pub fn trunc(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(trunc, v, m)
+}
+
+pub fn abs(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(abs, v, m)
+}
+
+// NaN is ignored if the other side is a number
+pub fn min(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(min, v, m, binary)
 }
 
-pub fn r#mod(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
-    math!(% , v, m)
+pub fn max(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    math_fn!(max, v, m, binary)
 }
 
 #[cfg(test)]
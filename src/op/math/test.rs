@@ -1,10 +1,11 @@
+use crate::code::Arg;
 use crate::lex::Tok;
 use crate::mem::Mem;
 use crate::error::Error;
 
 #[test]
 fn add(){
-    let v = vec![Tok::Num(1.0), Tok::Num(2.0)];
+    let v = vec![Arg::Num(1.0), Arg::Num(2.0)];
     let mut m = Mem::new();
     super::add(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 3.0);
@@ -12,7 +13,7 @@ fn add(){
 
 #[test]
 fn sub(){
-    let v = vec![Tok::Num(1.0), Tok::Num(2.0)];
+    let v = vec![Arg::Num(1.0), Arg::Num(2.0)];
     let mut m = Mem::new();
     super::sub(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), -1.0);
@@ -20,7 +21,7 @@ fn sub(){
 
 #[test]
 fn mul(){
-    let v = vec![Tok::Num(1.0), Tok::Num(2.0)];
+    let v = vec![Arg::Num(1.0), Arg::Num(2.0)];
     let mut m = Mem::new();
     super::mul(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 2.0);
@@ -28,7 +29,7 @@ fn mul(){
 
 #[test]
 fn div(){
-    let v = vec![Tok::Num(1.0), Tok::Num(2.0)];
+    let v = vec![Arg::Num(1.0), Arg::Num(2.0)];
     let mut m = Mem::new();
     super::div(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 0.5);
@@ -36,7 +37,7 @@ fn div(){
 
 #[test]
 fn r#mod(){
-    let v = vec![Tok::Num(11.0), Tok::Num(10.0)];
+    let v = vec![Arg::Num(11.0), Arg::Num(10.0)];
     let mut m = Mem::new();
     super::r#mod(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), 1.0);
@@ -44,7 +45,7 @@ fn r#mod(){
 
 #[test]
 fn div_by_zero(){
-    let v = vec![Tok::Num(1.0), Tok::Num(0.0)];
+    let v = vec![Arg::Num(1.0), Arg::Num(0.0)];
     let mut m = Mem::new();
     super::div(&v, &mut m).unwrap();
     assert_eq!(m.mem_at(0).unwrap(), f64::INFINITY);
@@ -52,27 +53,147 @@ fn div_by_zero(){
 
 #[test]
 fn div_zero_by_zero(){
-    let v = vec![Tok::Num(0.0), Tok::Num(0.0)];
+    let v = vec![Arg::Num(0.0), Arg::Num(0.0)];
     let mut m = Mem::new();
     super::div(&v, &mut m).unwrap();
-    assert_eq!(m.mem_at(0).unwrap().is_nan(), true);
+    assert!(m.mem_at(0).unwrap().is_nan());
 }
 
 #[test]
 fn add_incorrect_args_num(){
-    let v = vec![Tok::Ltl("asdas".to_string()), Tok::Num(0.0), Tok::Ltl("asd".to_string())];
+    let v = vec![Arg::Ltl(-1), Arg::Num(0.0), Arg::Ltl(-1)];
     let mut m = Mem::new();
     let r = super::add(&v, &mut m).unwrap_err();
-    assert_matches!(Error::WrongArgCount(2, 3), r);
+    assert_matches!(r, Error::WrongArgCount(2, 3));
 }
 
 #[test]
 fn add_incorrect_args_type(){
-    let v = vec![Tok::Ltl("asdas".to_string()), Tok::Ltl("asdasd".to_string())];
+    let v = vec![Arg::Ltl(-1), Arg::Ltl(-1)];
     let mut m = Mem::new();
     let r = super::add(&v, &mut m).unwrap_err();
-    let expected = Error::WrongArgType(
-            vec![Tok::NUM_STR, Tok::IDX_STR, Tok::VAR_STR], 
-            Tok::LTL_STR);
-    assert_matches!(r, expected);
+    assert_matches!(r, Error::Arg(0, ref e) if matches!(**e, Error::WrongArgType(_, Tok::LTL_STR)));
+}
+
+fn unary(f: fn(&[Arg], &mut Mem) -> Result<crate::op::Signal, Error>, x: f64) -> f64{
+    let v = vec![Arg::Num(x)];
+    let mut m = Mem::new();
+    f(&v, &mut m).unwrap();
+    m.mem_at(0).unwrap()
+}
+
+fn binary(f: fn(&[Arg], &mut Mem) -> Result<crate::op::Signal, Error>, l: f64, r: f64) -> f64{
+    let v = vec![Arg::Num(l), Arg::Num(r)];
+    let mut m = Mem::new();
+    f(&v, &mut m).unwrap();
+    m.mem_at(0).unwrap()
+}
+
+#[test]
+fn sqrt_pow_exp_log(){
+    assert_eq!(unary(super::sqrt, 16.0), 4.0);
+    assert!(unary(super::sqrt, -1.0).is_nan());
+    assert_eq!(binary(super::pow, 2.0, 10.0), 1024.0);
+    assert_eq!(binary(super::pow, 4.0, 0.5), 2.0);
+    assert_eq!(unary(super::exp, 0.0), 1.0);
+    assert_eq!(unary(super::ln, std::f64::consts::E), 1.0);
+    assert_eq!(unary(super::log10, 1000.0), 3.0);
+    assert_eq!(unary(super::ln, 0.0), f64::NEG_INFINITY);
+}
+
+#[test]
+fn trig(){
+    use std::f64::consts::PI;
+    assert_eq!(unary(super::sin, 0.0), 0.0);
+    assert_eq!(unary(super::cos, 0.0), 1.0);
+    assert!((unary(super::tan, PI / 4.0) - 1.0).abs() < 1e-12);
+    assert_eq!(binary(super::atan2, 1.0, 0.0), PI / 2.0);
+    assert_eq!(binary(super::atan2, 0.0, -1.0), PI);
+}
+
+#[test]
+fn rounding(){
+    assert_eq!(unary(super::floor, -1.5), -2.0);
+    assert_eq!(unary(super::ceil, -1.5), -1.0);
+    assert_eq!(unary(super::round, -1.5), -2.0);
+    assert_eq!(unary(super::round, 2.5), 3.0);
+    assert_eq!(unary(super::trunc, -1.7), -1.0);
+    assert_eq!(unary(super::abs, -3.0), 3.0);
+}
+
+#[test]
+fn min_max(){
+    assert_eq!(binary(super::min, 1.0, -2.0), -2.0);
+    assert_eq!(binary(super::max, 1.0, -2.0), 1.0);
+    assert_eq!(binary(super::min, f64::NAN, 3.0), 3.0);
+}
+
+#[test]
+fn math_fn_args(){
+    let v = vec![Arg::Num(1.0), Arg::Num(2.0)];
+    let mut m = Mem::new();
+    assert_matches!(super::sqrt(&v, &mut m), Err(Error::WrongArgCount(1, 2)));
+    let v = vec![Arg::Ltl(-1)];
+    assert_matches!(super::abs(&v, &mut m),
+        Err(Error::Arg(0, ref e)) if matches!(**e, Error::WrongArgType(_, Tok::LTL_STR)));
+}
+
+#[test]
+fn strict_div_by_zero(){
+    let mut m = Mem::new();
+    m.set_strict(true);
+    let v = vec![Arg::Num(1.0), Arg::Num(0.0)];
+    // divisor is the cause
+    assert_matches!(super::div(&v, &mut m),
+        Err(Error::Arg(1, ref e)) if matches!(**e, Error::DivByZero));
+    assert_matches!(super::r#mod(&v, &mut m),
+        Err(Error::Arg(1, ref e)) if matches!(**e, Error::DivByZero));
+    // [0] is untouched
+    assert_eq!(m.mem_at(0).unwrap(), 0.0);
+    let v = vec![Arg::Num(1.0), Arg::Num(4.0)];
+    super::div(&v, &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 0.25);
+}
+
+#[test]
+fn strict_overflow(){
+    let mut m = Mem::new();
+    m.set_strict(true);
+    let v = vec![Arg::Num(super::super::MAX_EXACT_INT - 1.0), Arg::Num(1.0)];
+    assert_matches!(super::add(&v, &mut m), Err(Error::IntegerOverflow(_)));
+    super::sub(&v, &mut m).unwrap();
+    let v = vec![Arg::Num(2.0), Arg::Num(60.0)];
+    assert_matches!(super::pow(&v, &mut m), Err(Error::IntegerOverflow(_)));
+    let v = vec![Arg::Num(1e300), Arg::Num(1e300)];
+    assert_matches!(super::mul(&v, &mut m), Err(Error::IntegerOverflow(_)));
+    // non integer operands are not checked
+    let v = vec![Arg::Num(1e308), Arg::Num(0.1)];
+    super::div(&v, &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), f64::INFINITY);
+}
+
+#[test]
+fn strict_nan(){
+    let mut m = Mem::new();
+    m.set_strict(true);
+    let v = vec![Arg::Num(-1.0)];
+    assert_matches!(super::sqrt(&v, &mut m),
+        Err(Error::Arg(0, ref e)) if matches!(**e, Error::NanResult));
+    let v = vec![Arg::Num(f64::INFINITY), Arg::Num(f64::INFINITY)];
+    assert_matches!(super::sub(&v, &mut m), Err(Error::NanResult));
+    // NaN operand is passed on
+    let v = vec![Arg::Num(f64::NAN), Arg::Num(1.0)];
+    super::add(&v, &mut m).unwrap();
+    assert!(m.mem_at(0).unwrap().is_nan());
+}
+
+#[test]
+fn lenient(){
+    let mut m = Mem::new();
+    let v = vec![Arg::Num(1.0), Arg::Num(0.0)];
+    super::r#mod(&v, &mut m).unwrap();
+    assert!(m.mem_at(0).unwrap().is_nan());
+    let v = vec![Arg::Num(-1.0)];
+    super::sqrt(&v, &mut m).unwrap();
+    assert!(m.mem_at(0).unwrap().is_nan());
 }
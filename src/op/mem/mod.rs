@@ -1,26 +1,27 @@
-use crate::lex::Tok;
+use crate::code::Arg;
 use crate::mem::*;
 use super::*;
 use crate::error::Error;
 
 // Assignment, read value
 //      mov: des(WPtr), src(Value)
-pub fn mov(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn mov(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 2);
-    let src_val = v[1].get_value(&*m)?;
-    v[0].write_value(m, src_val)?;
+    let src_val = v[1].get_value(&*m).arg(1)?;
+    v[0].write_value(m, src_val).arg(0)?;
     Ok(Signal::None)
 }
 
-// Memcpy. When src = Ltl, a new ltl is created and its idx is used as src idx
+// Memcpy. When src = Ltl, its interned copy in nmem is used as src
 //      cpy: des(WPtr), src(Ptr), size(Value)
-pub fn cpy(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn cpy(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 3);
-    let mut des_idx = v[0].get_loc(m)?;
-    let mut src_idx = v[1].get_loc(m)?;
-    let size_val = v[2].get_uint(m)?;
+    let mut des_idx = v[0].get_loc(m).arg(0)?;
+    let mut src_idx = v[1].get_loc(m).arg(1)?;
+    let size_val = v[2].get_uint(m).arg(2)?;
     for _ in 0..size_val {
-        Tok::Idx(Idx::Num(des_idx)).write_value(m, m.mem_at(src_idx).unwrap())?;
+        let val = m.mem_at(src_idx).arg(1)?;
+        Arg::Idx(des_idx).write_value(m, val).arg(0)?;
         mem::idx_incr(&mut des_idx, 1);
         mem::idx_incr(&mut src_idx, 1);
     }
@@ -29,50 +30,80 @@ pub fn cpy(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
 
 // Creates or update $name with index = idx
 //      var: name(Sym), idx(Ptr)
-pub fn var(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn var(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 2);
-    let var = v[0].get_sym()?;
-    let idx = v[1].get_loc(m)?;
-    m.var_set(var.idx, idx);
+    let var = v[0].get_var().arg(0)?;
+    let idx = v[1].get_loc(m).arg(1)?;
+    m.var_set(var, idx);
     Ok(Signal::None)
 }
 
 // Returns the index of ptr as value
 //      loc: ptr(Ptr)
-pub fn loc(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn loc(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 1);
-    let idx = v[0].get_loc(m)?;
+    let idx = v[0].get_loc(m).arg(0)?;
     m.mem_set(0, idx as f64)?;
     Ok(Signal::None)
 }
 
 // Used to iterate->read/write pmem, potentially can be used to do stack operations
 //      incr: var(Var), num(Value)
-pub fn incr(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn incr(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 2);
-    if let Tok::Var(var) = &v[0] {
-        let incr_val = v[1].get_int(m)?;
-        let mut var_idx = m.var_find(var)?;
-        idx_incr(&mut var_idx, incr_val as isize);
-        // also update var_idx of the variable
-        m.var_set(var.idx, var_idx);
-        return Ok(Signal::None)
-    }else {
-        return Err(Error::WrongArgType(
-                vec![Tok::VAR_STR],
-                v[0].to_type_str()))
-    }
+    let var = v[0].get_var().arg(0)?;
+    let incr_val = v[1].get_int(m).arg(1)?;
+    let mut var_idx = m.var_find(var).arg(0)?;
+    idx_incr(&mut var_idx, incr_val as isize);
+    // also update var_idx of the variable
+    m.var_set(var, var_idx);
+    Ok(Signal::None)
 }
 
 // Push slots to pmem
 //      allc: size(Value)
-pub fn allc(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn allc(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
     argc_guard!(v, 1);
-    let size = v[0].get_uint(&m)?;
+    let size = v[0].get_uint(m).arg(0)?;
+    m.pmem_reserve(size as usize)?;
     m.pmem_allc(&vec![0f64; size as usize]);
     m.mem_set(0, size as f64)?;
     Ok(Signal::None)
 }
 
+// Allocate zeroed block in heap, [0] set to its base.
+// Size of block is at base-1
+//      alloc: size(Value)
+pub fn alloc(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    argc_guard!(v, 1);
+    let size = v[0].get_uint(m).arg(0)?;
+    let base = m.heap_alloc(size as usize)?;
+    m.mem_set(0, base as f64)?;
+    Ok(Signal::None)
+}
+
+// Free block allocated by alloc or realloc
+//      free: ptr(Ptr)
+pub fn free(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    argc_guard!(v, 1);
+    let ptr = v[0].get_loc(m).arg(0)?;
+    m.heap_free(ptr).arg(0)?;
+    Ok(Signal::None)
+}
+
+// Resize block, [0] set to its base which may have moved
+//      realloc: ptr(Ptr), size(Value)
+pub fn realloc(v: &[Arg], m: &mut Mem) -> Result<Signal, Error>{
+    argc_guard!(v, 2);
+    let ptr = v[0].get_loc(m).arg(0)?;
+    let size = v[1].get_uint(m).arg(1)?;
+    let base = m.heap_realloc(ptr, size as usize).map_err(|e| match e {
+        Error::PmemLimit(_) => e,
+        _ => e.arg(0),
+    })?;
+    m.mem_set(0, base as f64)?;
+    Ok(Signal::None)
+}
+
 #[cfg(test)]
 mod test;
@@ -1,9 +1,9 @@
-use crate::lex::*;
+use crate::code::Arg;
 use crate::mem::Mem;
 
 #[test]
 fn mov(){
-    let v = vec![Tok::Idx(Idx::Num(10)), Tok::Num(100.0)];
+    let v = vec![Arg::Idx(10), Arg::Num(100.0)];
     let mut m = Mem::new();
     m.pmem_allc(&[0.0; 10]);
     super::mov(&v, &mut m).unwrap();
@@ -14,8 +14,8 @@ fn mov(){
 
 #[test]
 fn cpy(){
-    let v = vec![Tok::Idx(Idx::Num(1)), Tok::Ltl("asdasd".to_string()), Tok::Num(6.0)];
     let mut m = Mem::new();
+    let v = vec![Arg::Idx(1), Arg::Ltl(m.ltl_intern("asdasd")), Arg::Num(6.0)];
     m.pmem_allc(&[0.0; 8]);
     super::cpy(&v, &mut m).unwrap();
     assert_eq!(
@@ -25,38 +25,52 @@ fn cpy(){
 
 #[test]
 fn var(){
-    let v = vec![Tok::Sym(HashIdx::new("A", 0)), Tok::Idx(Idx::Num(10))];
+    let v = vec![Arg::Var(0), Arg::Idx(10)];
     let mut m = Mem::new();
-    m.var_add(10);
+    m.var_add(0);
     super::var(&v, &mut m).unwrap();
-    if let Tok::Sym(hi) = &v[0] {
-        assert_eq!(m.var_find(&hi).unwrap(), 10);
-    }
+    assert_eq!(m.var_find(0).unwrap(), 10);
 }
 
 #[test]
 fn incr(){
-    let v = vec![Tok::Var(HashIdx::new("A", 0)), Tok::Num(10.0)];
-    let v2 = vec![Tok::Var(HashIdx::new("A", 0)), Tok::Num(-8.0)];
+    let v = vec![Arg::Var(0), Arg::Num(10.0)];
+    let v2 = vec![Arg::Var(0), Arg::Num(-8.0)];
     let mut m = Mem::new();
-    if let Tok::Sym(hi) = &v[0]{
-        m.var_add(0);
-        super::incr(&v, &mut m).unwrap();
-        assert_eq!(m.var_find(&hi).unwrap(), 10);
-        m.var_set(hi.idx, -1);
-        super::incr(&v, &mut m).unwrap();
-        assert_eq!(m.var_find(&hi).unwrap(), -11);
-        m.var_set(hi.idx, 10);
-        super::incr(&v2, &mut m).unwrap();
-        assert_eq!(m.var_find(&hi).unwrap(), 2);
-    }
+    m.var_add(0);
+    super::incr(&v, &mut m).unwrap();
+    assert_eq!(m.var_find(0).unwrap(), 10);
+    m.var_set(0, -1);
+    super::incr(&v, &mut m).unwrap();
+    assert_eq!(m.var_find(0).unwrap(), -11);
+    m.var_set(0, 10);
+    super::incr(&v2, &mut m).unwrap();
+    assert_eq!(m.var_find(0).unwrap(), 2);
 }
 
 #[test]
 fn allc(){
-    let v = vec![Tok::Num(100.0)];
+    let v = vec![Arg::Num(100.0)];
     let mut m = Mem::new();
     let o_size = m.pmem_len();
     super::allc(&v, &mut m).unwrap();
     assert_eq!(m.pmem_len()-o_size, 100);
 }
+
+#[test]
+fn alloc_free(){
+    let mut m = Mem::new();
+    super::alloc(&[Arg::Num(4.0)], &mut m).unwrap();
+    let base = m.mem_at(0).unwrap();
+    assert_eq!(base, 5.0);
+    m.pmem_allc(&[base]);
+    // ptr held in a slot
+    super::realloc(&[Arg::Ind(9, 1), Arg::Num(2.0)], &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), base);
+    super::free(&[Arg::Ind(9, 1)], &mut m).unwrap();
+    assert_matches!(super::free(&[Arg::Idx(5)], &mut m),
+        Err(crate::error::Error::Arg(0, ref e)) if matches!(**e, crate::error::Error::DoubleFree(5)));
+    // allc is unaffected by heap
+    super::allc(&[Arg::Num(2.0)], &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 2.0);
+}
@@ -1,8 +1,8 @@
 use ahash::AHashMap;
-use crate::lex::*;
 use crate::mem::Mem;
-use crate::code::Code;
-use crate::error::Error;
+use crate::code::{Code, Arg};
+use crate::lex::{Tok, Idx};
+use crate::error::{Error, ArgErr};
 
 #[derive(Clone, Debug, PartialEq)]
 pub enum Signal{
@@ -10,16 +10,18 @@ pub enum Signal{
     SetLbl(usize),
     SetAls(usize, usize),
     Jmp(usize),
+    Call(usize),
+    Ret,
     Src(String),
+    Trap(usize),
+    Exit(i32),
 }
 
 impl Signal{
     pub fn respond(
         &self, 
         m: &mut Mem, 
-        code: &mut Code, 
-        op_idx_table: &AHashMap<&'static str, usize>,
-        op_vec: &[OpFunc]
+        code: &mut Code
     ) -> Result<(), Error>{
         match *self {
             Signal::None => (),
@@ -28,6 +30,16 @@ impl Signal{
                 code.ptr_set(idx);
                 return Ok(());
             },
+            Signal::Call(idx) => {
+                // save the line after call, then jump
+                m.ret_push(code.ptr()+1)?;
+                code.ptr_set(idx);
+                return Ok(());
+            },
+            Signal::Ret => {
+                code.ptr_set(m.ret_pop()?);
+                return Ok(());
+            },
             Signal::SetLbl(label) => {
                 // Update line number for label. 
                 // Set to current line + 1 to prevent re-updating
@@ -38,24 +50,95 @@ impl Signal{
                 // Update alias to loc
                 m.label_set(alias, loc);
             }
-            Signal::Src(ref s) => {
-                let mut src = Code::new();
-                crate::read_from_file(
-                    s,
-                    m,
-                    &mut src,
-                    op_idx_table)?;
-                crate::run(m, &mut src, op_idx_table, op_vec)?;
-            }
+            // the sourced file is loaded and run by Interpreter
+            Signal::Src(_) => (),
+            // registered by Interpreter, which knows the code stack
+            Signal::Trap(_) => (),
+            // stop at current line
+            Signal::Exit(_) => return Ok(()),
         };
         code.ptr_incr();
         Ok(())
     }
 }
 
-pub type OpFunc = fn(&[Tok], &mut Mem) -> Result<Signal, Error>;
+pub type OpFunc = Box<dyn Fn(&[Arg], &mut Mem) -> Result<Signal, Error> + Send>;
+
+// Kind of argument accepted by an op, checked during preprocess
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ArgKind{
+    Value,  // Num, Idx, Var
+    Ptr,  // Idx, Var, Ltl
+    WPtr,  // Idx, Var. Idx must not be negative
+    Var,
+    Lbl,  // Sym that refers to a label
+    VarName,  // Sym that names a var, created during preprocess
+    Sym,
+    PtrOrSym,
+    Fmt,  // Ltl, followed by one arg per conversion in it
+}
+
+impl ArgKind{
+    fn expect(self) -> Vec<&'static str>{
+        match self {
+            ArgKind::Value => vec![Tok::NUM_STR, Tok::IDX_STR, Tok::VAR_STR],
+            ArgKind::Ptr => vec![Tok::IDX_STR, Tok::VAR_STR, Tok::LTL_STR],
+            ArgKind::WPtr => vec![Tok::IDX_STR, Tok::VAR_STR],
+            ArgKind::Var => vec![Tok::VAR_STR],
+            ArgKind::Lbl | ArgKind::VarName | ArgKind::Sym => vec![Tok::SYM_STR],
+            ArgKind::PtrOrSym => 
+                vec![Tok::IDX_STR, Tok::VAR_STR, Tok::LTL_STR, Tok::SYM_STR],
+            ArgKind::Fmt => vec![Tok::LTL_STR],
+        }
+    }
+
+    pub fn check(self, t: &Tok) -> Result<(), Error>{
+        let ok = match (self, t) {
+            (ArgKind::WPtr, Tok::Idx(Idx::Num(n))) if *n < 0 =>
+                return Err(Error::WriteToNMem(*n)),
+            (ArgKind::Value, Tok::Num(_) | Tok::Idx(_) | Tok::Var(_)) => true,
+            (ArgKind::Ptr, Tok::Idx(_) | Tok::Var(_) | Tok::Ltl(_)) => true,
+            (ArgKind::WPtr, Tok::Idx(_) | Tok::Var(_)) => true,
+            (ArgKind::Var, Tok::Var(_)) => true,
+            (ArgKind::Lbl | ArgKind::VarName | ArgKind::Sym, Tok::Sym(_)) => true,
+            (ArgKind::PtrOrSym, Tok::Idx(_) | Tok::Var(_) | Tok::Ltl(_) | Tok::Sym(_)) => true,
+            (ArgKind::Fmt, Tok::Ltl(_)) => true,
+            _ => false,
+        };
+        if ok {
+            Ok(())
+        }else{
+            Err(Error::WrongArgType(self.expect(), t.to_type_str()))
+        }
+    }
+}
+
+// Args of an op, in order
+pub type Sig = &'static [ArgKind];
+
+// Check args against signature.
+// Error comes with index of the offending arg
+pub fn check_args(sig: Sig, args: &[Tok]) -> Result<(), (Error, usize)>{
+    let mut kinds = sig.to_vec();
+    // Fmt is last in signature, its conversions decide the args after it
+    if let Some(ArgKind::Fmt) = sig.last() {
+        if let Some(Tok::Ltl(f)) = args.get(sig.len()-1) {
+            let pieces = extra::parse_fmt(f.as_bytes()).map_err(|e| (e, sig.len()-1))?;
+            kinds.extend(pieces.iter().filter_map(|p| match p {
+                extra::Piece::Conv(c) => Some(c.kind()),
+                extra::Piece::Text(_) => None,
+            }));
+        }
+    }
+    if args.len() != kinds.len() {
+        return Err((Error::WrongArgCount(kinds.len(), args.len()), 0));
+    }
+    for (i, (k, t)) in kinds.iter().zip(args).enumerate() {
+        k.check(t).map_err(|e| (e, i))?;
+    }
+    Ok(())
+}
 
-#[macro_use]
 macro_rules! argc_guard {
     ( $v:expr, $e:expr ) => {
         if $v.len() != $e {
@@ -64,92 +147,174 @@ macro_rules! argc_guard {
     }
 }
 
+// Integers below 2^53 in magnitude are stored exactly by f64.
+// 2^53 itself may be a rounded 2^53+1
+pub const MAX_EXACT_INT: f64 = 9007199254740992.0;  // 2^53
+
+// In strict mode, reject NaN produced from operands that are not NaN. 
+// For int_op, also reject results of integer operands out of exact range
+fn check_num(m: &Mem, operands: &[f64], result: f64, int_op: bool) -> Result<f64, Error>{
+    if !m.strict() {
+        return Ok(result);
+    }
+    if result.is_nan() && !operands.iter().any(|x| x.is_nan()) {
+        return Err(Error::NanResult);
+    }
+    let int_operands = operands.iter().all(|x| x.is_finite() && x.fract() == 0.0);
+    if int_op && int_operands && result.abs() >= MAX_EXACT_INT {
+        return Err(Error::IntegerOverflow(result));
+    }
+    Ok(result)
+}
+
 mod nop;
 mod mem;
 mod math;
 mod cmp;
 mod logic;
+mod bit;
 mod flow;
 mod sys;
 mod extra;
 mod r#extern;
 
 macro_rules! add_entry {
-    ( $h:ident, $v:ident, $c:ident, $o:ident ) => {
-        // push function pointer
-        $v.push($c::$o as OpFunc);
-        // add (op, func ptr) entry to hash table
-        $h.insert(stringify!($o), $v.len()-1);
+    ( $h:ident, $v:ident, $s:ident, $c:ident, $o:ident, [$( $k:ident ),*] ) => {
+        add_op($h, $v, $s, stringify!($o), &[$( ArgKind::$k ),*], $c::$o);
     };
 }
 
+// Add op to the table.
+// Returns None if name is taken
+pub fn add_op<F>(
+    h: &mut AHashMap<String, usize>,
+    v: &mut Vec<OpFunc>,
+    s: &mut Vec<Sig>,
+    name: &str,
+    sig: Sig,
+    f: F
+) -> Option<usize>
+where F: Fn(&[Arg], &mut Mem) -> Result<Signal, Error> + Send + 'static
+{
+    if h.contains_key(name) {
+        return None;
+    }
+    v.push(Box::new(f));
+    s.push(sig);
+    h.insert(name.to_owned(), v.len()-1);
+    Some(v.len()-1)
+}
+
 #[derive(FromPrimitive)]
 pub enum Opcode {
     Nop = 0,
-    Mov, Copy, Var, Loc, Incr, Allc,
+    Mov, Copy, Var, Loc, Incr, Allc, Alloc, Free, Realloc,
     Add, Sub, Mul, Div,
-    Mod, Eq, Ne, Gt, Lt,
+    Mod,
+    Sqrt, Pow, Exp, Ln, Log10, Sin, Cos, Tan, Atan2,
+    Floor, Ceil, Round, Trunc, Abs, Min, Max,
+    Eq, Ne, Gt, Lt, Ge, Le, Cmp, Aeq,
     And, Or, Not,
-    Jmp, Jc, Lbl, Als,
-    Exit, Open, Close, Read, Write,
+    Band, Bor, Bxor, Bnot, Shl, Shr, Sar,
+    Jmp, Jc, Lbl, Als, Call, Ret, Trap,
+    Exit, Open, Close, Read, Write, ReadNum, ReadInt,
     Src,
-    PrintNum,
+    PrintNum, PrintStr, Println, Strlen, Printf, ParseNum, ParseInt,
 }
 
-pub fn init_op_table(h: &mut AHashMap<&'static str, usize>, v: &mut Vec<OpFunc>){
-    add_entry!(h, v, nop, nop);
+pub fn init_op_table(
+    h: &mut AHashMap<String, usize>,
+    v: &mut Vec<OpFunc>,
+    s: &mut Vec<Sig>
+){
+    add_entry!(h, v, s, nop, nop, []);
+
+    add_entry!(h, v, s, mem, mov, [WPtr, Value]);
+    add_entry!(h, v, s, mem, cpy, [WPtr, Ptr, Value]);
+    add_entry!(h, v, s, mem, var, [VarName, Ptr]);
+    add_entry!(h, v, s, mem, loc, [Ptr]);
+    add_entry!(h, v, s, mem, incr, [Var, Value]);
+    add_entry!(h, v, s, mem, allc, [Value]);
+    add_entry!(h, v, s, mem, alloc, [Value]);
+    add_entry!(h, v, s, mem, free, [Ptr]);
+    add_entry!(h, v, s, mem, realloc, [Ptr, Value]);
 
-    add_entry!(h, v, mem, mov);
-    add_entry!(h, v, mem, cpy);
-    add_entry!(h, v, mem, var);
-    add_entry!(h, v, mem, loc);
-    add_entry!(h, v, mem, incr);
-    add_entry!(h, v, mem, allc);
+    add_entry!(h, v, s, math, add, [Value, Value]);
+    add_entry!(h, v, s, math, sub, [Value, Value]);
+    add_entry!(h, v, s, math, mul, [Value, Value]);
+    add_entry!(h, v, s, math, div, [Value, Value]);
 
-    add_entry!(h, v, math, add);
-    add_entry!(h, v, math, sub);
-    add_entry!(h, v, math, mul);
-    add_entry!(h, v, math, div);
+    add_op(h, v, s, "mod", &[ArgKind::Value, ArgKind::Value], math::r#mod);
 
-    v.push(math::r#mod as OpFunc);
-    h.insert("mod", v.len()-1);
+    add_entry!(h, v, s, math, sqrt, [Value]);
+    add_entry!(h, v, s, math, pow, [Value, Value]);
+    add_entry!(h, v, s, math, exp, [Value]);
+    add_entry!(h, v, s, math, ln, [Value]);
+    add_entry!(h, v, s, math, log10, [Value]);
+    add_entry!(h, v, s, math, sin, [Value]);
+    add_entry!(h, v, s, math, cos, [Value]);
+    add_entry!(h, v, s, math, tan, [Value]);
+    add_entry!(h, v, s, math, atan2, [Value, Value]);
+    add_entry!(h, v, s, math, floor, [Value]);
+    add_entry!(h, v, s, math, ceil, [Value]);
+    add_entry!(h, v, s, math, round, [Value]);
+    add_entry!(h, v, s, math, trunc, [Value]);
+    add_entry!(h, v, s, math, abs, [Value]);
+    add_entry!(h, v, s, math, min, [Value, Value]);
+    add_entry!(h, v, s, math, max, [Value, Value]);
 
-    add_entry!(h, v, cmp, eq);
-    add_entry!(h, v, cmp, ne);
-    add_entry!(h, v, cmp, gt);
-    add_entry!(h, v, cmp, lt);
+    add_entry!(h, v, s, cmp, eq, [Value, Value]);
+    add_entry!(h, v, s, cmp, ne, [Value, Value]);
+    add_entry!(h, v, s, cmp, gt, [Value, Value]);
+    add_entry!(h, v, s, cmp, lt, [Value, Value]);
+    add_entry!(h, v, s, cmp, ge, [Value, Value]);
+    add_entry!(h, v, s, cmp, le, [Value, Value]);
+    add_entry!(h, v, s, cmp, cmp, [Value, Value]);
+    add_entry!(h, v, s, cmp, aeq, [Value, Value, Value]);
 
-    add_entry!(h, v, logic, and);
-    add_entry!(h, v, logic, or);
-    add_entry!(h, v, logic, not);
+    add_entry!(h, v, s, logic, and, [Value, Value]);
+    add_entry!(h, v, s, logic, or, [Value, Value]);
+    add_entry!(h, v, s, logic, not, [Value]);
 
-    add_entry!(h, v, flow, jmp);
-    add_entry!(h, v, flow, jc);
-    add_entry!(h, v, flow, lbl);
-    add_entry!(h, v, flow, als);
+    add_entry!(h, v, s, bit, band, [Value, Value]);
+    add_entry!(h, v, s, bit, bor, [Value, Value]);
+    add_entry!(h, v, s, bit, bxor, [Value, Value]);
+    add_entry!(h, v, s, bit, bnot, [Value]);
+    add_entry!(h, v, s, bit, shl, [Value, Value]);
+    add_entry!(h, v, s, bit, shr, [Value, Value]);
+    add_entry!(h, v, s, bit, sar, [Value, Value]);
 
-    add_entry!(h, v, sys, exit);
-    add_entry!(h, v, sys, open);
-    add_entry!(h, v, sys, close);
-    add_entry!(h, v, sys, read);
-    add_entry!(h, v, sys, write);
+    add_entry!(h, v, s, flow, jmp, [Lbl]);
+    add_entry!(h, v, s, flow, jc, [Value, Lbl]);
+    add_entry!(h, v, s, flow, lbl, [Lbl]);
+    add_entry!(h, v, s, flow, als, [Lbl, Lbl]);
+    add_entry!(h, v, s, flow, call, [Lbl]);
+    add_entry!(h, v, s, flow, ret, []);
+    add_entry!(h, v, s, flow, trap, [Lbl]);
 
-    add_entry!(h, v, r#extern, src);
+    add_entry!(h, v, s, sys, exit, [Value]);
+    add_entry!(h, v, s, sys, open, [PtrOrSym, Value]);
+    add_entry!(h, v, s, sys, close, [Value]);
+    add_entry!(h, v, s, sys, read, [Value, WPtr, Value]);
+    add_entry!(h, v, s, sys, write, [Value, Ptr, Value]);
+    add_entry!(h, v, s, sys, read_num, [Value]);
+    add_entry!(h, v, s, sys, read_int, [Value]);
 
-    add_entry!(h, v, extra, print_num);
+    add_entry!(h, v, s, r#extern, src, [Sym]);
+
+    add_entry!(h, v, s, extra, print_num, [Value, Value]);
+    add_entry!(h, v, s, extra, print_str, [Value, Ptr]);
+    add_entry!(h, v, s, extra, println, [Value, Ptr]);
+    add_entry!(h, v, s, extra, strlen, [Ptr]);
+    add_entry!(h, v, s, extra, printf, [Value, Fmt]);
+    add_entry!(h, v, s, extra, parse_num, [Ptr]);
+    add_entry!(h, v, s, extra, parse_int, [Ptr]);
 }
 
 pub fn exec(func_vec: &[OpFunc], m: &mut Mem, c: &Code) -> Result<Signal, Error>{
-    let v = c.curr().unwrap();
-    if let Tok::Sym(ref hi) = v[0] {
-        // lookup function pointer and execute
-        func_vec[hi.idx](&v[1..], m)
-    }else{
-        // should not be executed 
-        // because there is already a type check during preprocess
-        // written just to let rustc to compile
-        Err(Error::WrongTokTypeForOp(v[0].to_type_str()))
-    }
+    let inst = c.curr().unwrap();
+    // lookup function pointer and execute
+    func_vec[inst.op](c.args(&inst), m)
 }
 
 #[cfg(test)]
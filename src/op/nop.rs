@@ -1,8 +1,8 @@
-use crate::lex::Tok;
+use crate::code::Arg;
 use crate::mem::Mem;
 use crate::op::Signal;
 use crate::error::Error;
 
-pub fn nop(_: &[Tok], _: &mut Mem) -> Result<Signal, Error>{
+pub fn nop(_: &[Arg], _: &mut Mem) -> Result<Signal, Error>{
     Ok(Signal::None)
 }
@@ -1,85 +1,185 @@
 use crate::error::Error;
-use crate::lex::Tok;
+use crate::code::Arg;
 use crate::mem::{Mem, idx_incr};
-use std::io::{ Write, Read };
-use std::fs::{ File, OpenOptions };
-use std::os::unix::io::{ FromRawFd, IntoRawFd };
+use std::io::Read;
+use std::fs::OpenOptions;
+use crate::stream::Stream;
 use super::*;
 
+// longest token read by read_num
 const MAX_INPUT: usize = 1024;
 
+// In nonfatal io mode, [1] is set to 0 on success. 
+// On io error, [0] is set to -1 and [1] to Error::code, 
+// execution continues
+fn nonfatal(m: &mut Mem, r: Result<Signal, Error>) -> Result<Signal, Error> {
+    if !m.nonfatal_io() {
+        return r;
+    }
+    match r {
+        Ok(s) => {
+            m.mem_set(1, 0.0)?;
+            Ok(s)
+        },
+        Err(e) if is_io(&e) => {
+            m.mem_set(0, -1.0)?;
+            m.mem_set(1, e.code() as f64)?;
+            Ok(Signal::None)
+        },
+        Err(e) => Err(e),
+    }
+}
+
+fn is_io(e: &Error) -> bool {
+    match e {
+        Error::Arg(_, e) => is_io(e),
+        Error::IoError(_) | Error::BadFileDescriptor(_) => true,
+        _ => false,
+    }
+}
+
 //      exit: exit_code(Value)
-pub fn exit(v: &[Tok], m: &mut Mem) -> Result<Signal, Error> {
+pub fn exit(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
     argc_guard!(v, 1);
-    let exit_code = v[0].get_value(m)?;
-    std::process::exit(exit_code as i32);
+    let exit_code = v[0].get_value(m).arg(0)?;
+    Ok(Signal::Exit(exit_code as i32))
 }
 
 // Writes to file descriptor. No mutex. 
-// [0] set to bytes slots written to fd
+// [0] set to bytes slots written to fd, less than size on short write.
+// Error only if nothing could be written
 //      write: fd(Value), ptr(Ptr), size(Value)
-pub fn write(v: &[Tok], m: &mut Mem) -> Result<Signal, Error> {
+pub fn write(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
+    let r = sys_write(v, m);
+    nonfatal(m, r)
+}
+
+fn sys_write(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
     argc_guard!(v, 3);
-    let fd = v[0].get_uint(m)? as i32;
+    let fd = v[0].get_uint(m).arg(0)? as i32;
     // check if fd is opened
-    if !m.fd[fd as usize] {
-        return Err(Error::BadFileDescriptor(fd));
-    }
-    // create file from fd
-    let mut f = unsafe { File::from_raw_fd(fd) };
-    let mut src_idx = v[1].get_loc(m)?;
-    let size = v[2].get_uint(m)?;
-    // read from mem and write to file
+    m.io_writer(fd).arg(0)?;
+    let mut src_idx = v[1].get_loc(m).arg(1)?;
+    let size = v[2].get_uint(m).arg(2)?;
+    // read from mem, then write to stream at once
+    let mut buf = Vec::new();
     for _ in 0..size as usize {
-        if let Err(e) = f.write(&[m.mem_at(src_idx)? as u8]){
-            return Err(Error::IoError(e));
-        }
+        buf.push(m.mem_at(src_idx).arg(1)? as u8);
         idx_incr(&mut src_idx, 1);
     }
-    // return file ownership to file descriptor, 
-    // don't close the file here
-    f.into_raw_fd();
-    m.mem_set(0, size as f64)?;
+    let w = m.io_writer(fd)?;
+    let mut written = 0;
+    while written < buf.len() {
+        match w.write(&buf[written..]) {
+            // stream can't take more
+            Ok(0) => break,
+            Ok(n) => written += n,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(e) if written == 0 => return Err(Error::IoError(e)),
+            Err(_) => break,
+        }
+    }
+    m.mem_set(0, written as f64)?;
     Ok(Signal::None)
 }
 
 // Read from fd. No mutex
-// [0] set to bytes slots read from fd
+// Reads at most size bytes, returns once some bytes are available, e.g. a line from a terminal.
+// ptr must have room for size bytes and terminating 0, checked before reading.
+// [0] set to bytes slots read from fd, 0 on EOF. 
+// Slot after the last byte is set to 0
 //      read: fd(Value), ptr(WPtr), size(Value)
-pub fn read(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn read(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
+    let r = sys_read(v, m);
+    nonfatal(m, r)
+}
+
+fn sys_read(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
     argc_guard!(v, 3);
-    let fd = v[0].get_uint(m)? as i32;
+    let fd = v[0].get_uint(m).arg(0)? as i32;
     // check if fd is opened
-    if !m.fd[fd as usize] {
-        return Err(Error::BadFileDescriptor(fd));
+    m.io_reader(fd).arg(0)?;
+    let des_idx = v[1].get_loc(m).arg(1)?;
+    if des_idx < 0 {
+        return Err(Error::WriteToNMem(des_idx).arg(1));
     }
-    // create file from fd
-    let mut f = unsafe { File::from_raw_fd(fd) };
-    let des_idx = v[1].get_loc(m)?;
-    let size = v[2].get_uint(m)?;
-    let size = size as usize;
-    let mut buf = [0; MAX_INPUT];
-    // read from file
-    if let Err(e) = f.read(&mut buf) {
-        return Err(Error::IoError(e));
+    let size = v[2].get_uint(m).arg(2)? as usize;
+    // slot of terminating 0
+    let end = (des_idx as usize).saturating_add(size).min(isize::MAX as usize);
+    if end >= m.pmem_len() {
+        return Err(Error::InvalidMemAccess(end as isize).arg(1));
     }
-    // transfer file ownership to fd prevent auto closing file
-    f.into_raw_fd();
-    // wrtie to mem
-    for i in 0..MAX_INPUT {
-        let c = buf[i] as f64;
-        if c == 0.0 || i == size {
-            m.mem_set(des_idx + i as isize, 0.0)?;
-            m.mem_set(0, i as f64)?;
-            break;
+    let mut buf = vec![0; size];
+    // read from stream
+    let r = m.io_reader(fd)?;
+    let n = loop {
+        match r.read(&mut buf) {
+            Ok(k) => break k,
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(Error::IoError(e)),
         }
-        m.mem_set(des_idx + i as isize, c)?;
+    };
+    // write to mem
+    for (i, c) in buf[..n].iter().enumerate() {
+        m.mem_set(des_idx + i as isize, *c as f64)?;
     }
+    m.mem_set(des_idx + n as isize, 0.0)?;
+    m.mem_set(0, n as f64)?;
     Ok(Signal::None)
 }
 
-// parse digits(boolean value) from right to left
-fn parse_open_options(mut o_val: u64) -> Result<OpenOptions, Error> {
+// Read whitespace-delimited token from fd and parse it as number.
+// [0] set to the number, [1] to 0 on success,
+// or to Error::code of Eof or ParseNumError. Io errors are handled as in read
+//      read_num: fd(Value)
+pub fn read_num(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
+    // status is set by sys_read_num, nonfatal only handles io errors
+    sys_read_num(v, m, false).or_else(|e| nonfatal(m, Err(e)))
+}
+
+// read_num that also sets [1] to Error::code of NotInterger if number is not an integer
+//      read_int: fd(Value)
+pub fn read_int(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
+    sys_read_num(v, m, true).or_else(|e| nonfatal(m, Err(e)))
+}
+
+fn sys_read_num(v: &[Arg], m: &mut Mem, int: bool) -> Result<Signal, Error> {
+    argc_guard!(v, 1);
+    let fd = v[0].get_uint(m).arg(0)? as i32;
+    match read_token(m.io_reader(fd).arg(0)?)? {
+        Some(tok) => extra::store_num(m, &tok, int),
+        None => {
+            m.mem_set(0, 0.0)?;
+            m.mem_set(1, Error::Eof.code() as f64)?;
+            Ok(Signal::None)
+        },
+    }
+}
+
+// Read one byte at a time so that input after the token
+// is left for later reads. None on EOF before any token
+fn read_token(f: &mut dyn Read) -> Result<Option<Vec<u8>>, Error> {
+    let mut tok = Vec::new();
+    let mut c = [0u8; 1];
+    while tok.len() < MAX_INPUT {
+        match f.read(&mut c) {
+            Ok(0) => break,
+            Ok(_) if c[0].is_ascii_whitespace() => {
+                if !tok.is_empty() {
+                    break;
+                }
+            },
+            Ok(_) => tok.push(c[0]),
+            Err(e) if e.kind() == std::io::ErrorKind::Interrupted => (),
+            Err(e) => return Err(Error::IoError(e)),
+        }
+    }
+    Ok(if tok.is_empty() { None } else { Some(tok) })
+}
+
+// parse digits(boolean value) from right to left:
+// read, write, append, truncate, create, create_new
+fn parse_open_options(mut o_val: u64) -> Result<[bool; 6], Error> {
     let mut options = [false; 6];
     for o in &mut options {
         *o = match o_val % 10 {
@@ -89,14 +189,18 @@ fn parse_open_options(mut o_val: u64) -> Result<OpenOptions, Error> {
         };
         o_val /= 10;
     }
-    Ok(OpenOptions::new()
+    Ok(options)
+}
+
+fn open_options(options: &[bool; 6]) -> OpenOptions {
+    OpenOptions::new()
         .read(options[0])
         .write(options[1])
         .append(options[2])
         .truncate(options[3])
         .create(options[4])
         .create_new(options[5])
-        .clone())
+        .clone()
 }
 
 // Open file and set [0] to fd
@@ -126,39 +230,50 @@ fn parse_open_options(mut o_val: u64) -> Result<OpenOptions, Error> {
 //           and will truncate it if it does.
 //      open:"text.txt",11010
 //
-pub fn open(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn open(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
+    let r = sys_open(v, m);
+    nonfatal(m, r)
+}
+
+fn sys_open(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
     argc_guard!(v, 2);
-    let name = if let Tok::Sym(idx) = &v[0] {
-        idx.sym.clone()
+    let name = if let Arg::Sym(s) = v[0] {
+        m.sym_at(s).to_owned()
     }else {
-        let name_ptr = v[0].get_loc(m)?;
-        m.read_ltl(name_ptr)?
+        let name_ptr = v[0].get_loc(m).arg(0)?;
+        m.read_ltl(name_ptr).arg(0)?
     };
     let f = {
-        let option = parse_open_options(v[1].get_uint(m)?)?;
-        match option.open(name) {
+        let option = v[1].get_uint(m).and_then(parse_open_options).arg(1)?;
+        if let Some(sandbox) = m.sandbox() {
+            let write = option[1..].iter().any(|o| *o);
+            sandbox.check_open(&name, write, option[3] || option[5]).arg(0)?;
+        }
+        m.io_reserve()?;
+        match open_options(&option).open(name) {
             Ok(f) => f,
-            Err(e) => return Err(Error::IoError(e)),
+            Err(e) => return Err(Error::IoError(e).arg(0)),
         }
     };
-    let fd = f.into_raw_fd();
-    m.fd[fd as usize] = true;
+    let fd = m.io_open(Stream::File(f))?;
     m.mem_set(0, fd as f64)?;
     Ok(Signal::None)
 }
 
 // Close fd
 //      close: fd(Value)
-pub fn close(v: &[Tok], m: &mut Mem) -> Result<Signal, Error>{
+pub fn close(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
+    let r = sys_close(v, m);
+    nonfatal(m, r)
+}
+
+fn sys_close(v: &[Arg], m: &mut Mem) -> Result<Signal, Error> {
     argc_guard!(v, 1);
-    let fd = v[0].get_uint(m)? as i32;
-    // check if fd is opened
-    if !m.fd[fd as usize] {
-        return Err(Error::BadFileDescriptor(fd));
-    }
-    // allow closing file automatically though drop
-    unsafe { File::from_raw_fd(fd) };
-    // mark fd as closed
-    m.fd[fd as usize] = false;
+    let fd = v[0].get_uint(m).arg(0)? as i32;
+    // stream is closed when dropped
+    m.io_close(fd).arg(0)?;
     Ok(Signal::None)
 }
+
+#[cfg(test)]
+mod test;
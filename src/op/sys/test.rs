@@ -0,0 +1,244 @@
+use crate::code::Arg;
+use crate::mem::Mem;
+use crate::error::Error;
+use crate::stream::{Capture, Stream};
+
+fn missing(m: &mut Mem) -> Vec<Arg>{
+    let name = m.ltl_intern("/nonexistent/lli_sys_test");
+    vec![Arg::Ltl(name), Arg::Num(1.0)]
+}
+
+#[test]
+fn error_slot_allocated(){
+    let m = Mem::new();
+    assert_eq!(m.mem_at(1).unwrap(), 0.0);
+}
+
+#[test]
+fn open_fatal(){
+    let mut m = Mem::new();
+    let v = missing(&mut m);
+    assert_matches!(super::open(&v, &mut m),
+        Err(Error::Arg(0, ref e)) if matches!(**e, Error::IoError(_)));
+}
+
+#[test]
+fn open_nonfatal(){
+    let mut m = Mem::new();
+    m.set_nonfatal_io(true);
+    let v = missing(&mut m);
+    super::open(&v, &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), -1.0);
+    // ENOENT
+    assert_eq!(m.mem_at(1).unwrap(), 2.0);
+}
+
+#[test]
+fn bad_fd_nonfatal(){
+    let mut m = Mem::new();
+    m.set_nonfatal_io(true);
+    let v = vec![Arg::Num(100.0)];
+    super::close(&v, &mut m).unwrap();
+    assert_eq!(m.mem_at(1).unwrap(), Error::BadFileDescriptor(100).code() as f64);
+    // fd beyond table is bad too
+    let v = vec![Arg::Num(1e6)];
+    super::close(&v, &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), -1.0);
+    // other errors are still fatal
+    let v = vec![Arg::Num(1.5)];
+    assert_matches!(super::close(&v, &mut m),
+        Err(Error::Arg(0, ref e)) if matches!(**e, Error::NegativeOrNotInterger(_)));
+}
+
+#[test]
+fn nonfatal_success_clears(){
+    let path = std::env::temp_dir().join("lli_sys_test_open");
+    std::fs::write(&path, "a").unwrap();
+    let mut m = Mem::new();
+    m.set_nonfatal_io(true);
+    m.mem_set(1, 5.0).unwrap();
+    let name = m.ltl_intern(path.to_str().unwrap());
+    super::open(&[Arg::Ltl(name), Arg::Num(1.0)], &mut m).unwrap();
+    assert_eq!(m.mem_at(1).unwrap(), 0.0);
+    let fd = m.mem_at(0).unwrap();
+    super::close(&[Arg::Num(fd)], &mut m).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn read_num(){
+    let path = std::env::temp_dir().join("lli_sys_test_read_num");
+    std::fs::write(&path, "  12.5\n-3 abc\n4.5").unwrap();
+    let mut m = Mem::new();
+    let name = m.ltl_intern(path.to_str().unwrap());
+    super::open(&[Arg::Ltl(name), Arg::Num(1.0)], &mut m).unwrap();
+    let fd = Arg::Num(m.mem_at(0).unwrap());
+    let status = |m: &Mem| (m.mem_at(0).unwrap(), m.mem_at(1).unwrap());
+    super::read_num(&[fd], &mut m).unwrap();
+    assert_eq!(status(&m), (12.5, 0.0));
+    super::read_int(&[fd], &mut m).unwrap();
+    assert_eq!(status(&m), (-3.0, 0.0));
+    super::read_num(&[fd], &mut m).unwrap();
+    assert_eq!(status(&m), (0.0, -2.0));
+    super::read_int(&[fd], &mut m).unwrap();
+    assert_eq!(status(&m), (0.0, Error::NotInterger(0.0).code() as f64));
+    super::read_num(&[fd], &mut m).unwrap();
+    assert_eq!(status(&m), (0.0, Error::Eof.code() as f64));
+    super::close(&[fd], &mut m).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    assert_matches!(super::read_num(&[fd], &mut m),
+        Err(Error::Arg(0, ref e)) if matches!(**e, Error::BadFileDescriptor(_)));
+}
+
+#[test]
+fn fd_limit(){
+    let path = std::env::temp_dir().join("lli_sys_test_fd_limit");
+    std::fs::write(&path, "a").unwrap();
+    let mut m = Mem::new();
+    m.set_nonfatal_io(true);
+    // stdin, stdout and stderr count
+    m.set_fd_limit(4);
+    let name = m.ltl_intern(path.to_str().unwrap());
+    let v = vec![Arg::Ltl(name), Arg::Num(1.0)];
+    super::open(&v, &mut m).unwrap();
+    let fd = m.mem_at(0).unwrap();
+    // not an io error, fatal even in nonfatal mode
+    assert_matches!(super::open(&v, &mut m), Err(Error::FdLimit(4)));
+    super::close(&[Arg::Num(fd)], &mut m).unwrap();
+    super::open(&v, &mut m).unwrap();
+    let fd = m.mem_at(0).unwrap();
+    super::close(&[Arg::Num(fd)], &mut m).unwrap();
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn open_sandbox(){
+    let d = std::env::temp_dir().join("lli_sys_test_sandbox");
+    std::fs::create_dir_all(&d).unwrap();
+    let mut sandbox = crate::sandbox::Sandbox::new();
+    sandbox.allow_write(&d).unwrap();
+    let mut m = Mem::new();
+    m.set_nonfatal_io(true);
+    m.set_sandbox(Some(sandbox));
+    let name = m.ltl_intern(d.join("f").to_str().unwrap());
+    // write and create
+    super::open(&[Arg::Ltl(name), Arg::Num(10010.0)], &mut m).unwrap();
+    let fd = m.mem_at(0).unwrap();
+    super::close(&[Arg::Num(fd)], &mut m).unwrap();
+    // truncate is only allowed in scratch, not an io error
+    assert_matches!(super::open(&[Arg::Ltl(name), Arg::Num(1010.0)], &mut m),
+        Err(Error::Arg(0, ref e)) if matches!(**e, Error::PermissionDenied(_)));
+    let name = m.ltl_intern("/etc/hostname");
+    assert_matches!(super::open(&[Arg::Ltl(name), Arg::Num(1.0)], &mut m),
+        Err(Error::Arg(0, ref e)) if matches!(**e, Error::PermissionDenied(_)));
+    std::fs::remove_dir_all(&d).unwrap();
+}
+
+// accepts up to n bytes, then nothing
+struct Short(usize);
+
+impl std::io::Write for Short {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize>{
+        let n = buf.len().min(self.0);
+        self.0 -= n;
+        Ok(n)
+    }
+    fn flush(&mut self) -> std::io::Result<()>{
+        Ok(())
+    }
+}
+
+// interrupted before every read
+struct Flaky(bool, &'static [u8]);
+
+impl std::io::Read for Flaky {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize>{
+        self.0 = !self.0;
+        if self.0 {
+            return Err(std::io::ErrorKind::Interrupted.into());
+        }
+        self.1.read(buf)
+    }
+}
+
+// at most n bytes per read
+struct Chunks(usize, &'static [u8]);
+
+impl std::io::Read for Chunks {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize>{
+        let n = buf.len().min(self.0);
+        self.1.read(&mut buf[..n])
+    }
+}
+
+#[test]
+fn write_buffer(){
+    let mut m = Mem::new();
+    let out = Capture::default();
+    m.io_attach(3, Stream::Out(Box::new(out.clone())));
+    m.io_attach(4, Stream::Out(Box::new(Short(1500))));
+    let base = m.heap_alloc(2000).unwrap();
+    for i in 0..2000 {
+        m.mem_set((base + i) as isize, (b'a' + (i % 26) as u8) as f64).unwrap();
+    }
+    let v = |fd| vec![Arg::Num(fd), Arg::Idx(base as isize), Arg::Num(2000.0)];
+    super::write(&v(3.0), &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 2000.0);
+    assert_eq!(out.contents().len(), 2000);
+    assert_eq!(&out.contents()[26..29], b"abc");
+    // short write
+    super::write(&v(4.0), &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 1500.0);
+    super::write(&v(4.0), &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 0.0);
+}
+
+#[test]
+fn read_buffer(){
+    let data: &'static [u8] = &[b'x'; 3000];
+    let mut m = Mem::new();
+    m.io_attach(3, Stream::In(Box::new(data)));
+    m.io_attach(4, Stream::In(Box::new(Flaky(false, b"a\0b"))));
+    let base = m.heap_alloc(4000).unwrap();
+    let v = |fd, size| vec![Arg::Num(fd), Arg::Idx(base as isize), Arg::Num(size)];
+    // past MAX_INPUT
+    super::read(&v(3.0, 2500.0), &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 2500.0);
+    assert_eq!(m.mem_at((base + 2499) as isize).unwrap(), b'x' as f64);
+    assert_eq!(m.mem_at((base + 2500) as isize).unwrap(), 0.0);
+    super::read(&v(3.0, 2500.0), &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 500.0);
+    // EOF
+    super::read(&v(3.0, 2500.0), &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 0.0);
+    // retried, 0 bytes are data
+    super::read(&v(4.0, 10.0), &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 3.0);
+    assert_eq!(m.mem_at((base + 2) as isize).unwrap(), b'b' as f64);
+}
+
+#[test]
+fn read_chunks(){
+    let data: &'static [u8] = &[b'y'; 250];
+    let mut m = Mem::new();
+    m.io_attach(3, Stream::In(Box::new(Chunks(7, data))));
+    let base = m.heap_alloc(200).unwrap();
+    let v = |size| vec![Arg::Num(3.0), Arg::Idx(base as isize), Arg::Num(size)];
+    // returns after the first chunk
+    super::read(&v(150.0), &mut m).unwrap();
+    assert_eq!(m.mem_at(0).unwrap(), 7.0);
+    assert_eq!(m.mem_at((base + 6) as isize).unwrap(), b'y' as f64);
+    assert_eq!(m.mem_at((base + 7) as isize).unwrap(), 0.0);
+    // no room for terminating 0, nothing is read
+    assert_matches!(super::read(&v(200.0), &mut m),
+        Err(Error::Arg(1, ref e)) if matches!(**e, Error::InvalidMemAccess(_)));
+    let mut total = 7.0;
+    loop {
+        super::read(&v(150.0), &mut m).unwrap();
+        match m.mem_at(0).unwrap() {
+            0.0 => break,
+            n => total += n,
+        }
+    }
+    assert_eq!(total, 250.0);
+}
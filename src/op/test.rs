@@ -1,16 +1,92 @@
 use ahash::AHashMap;
 use crate::lex::*;
 use crate::mem::Mem;
-use crate::code::Code;
+use crate::code::{Code, LineSrc};
+
+// builtin op table
+fn op_table() -> (AHashMap<String, usize>, Vec<super::OpFunc>, Vec<super::Sig>){
+    let mut op_idx_table = AHashMap::new();
+    let mut func_vec = Vec::new();
+    let mut op_sig = Vec::new();
+    super::init_op_table(&mut op_idx_table, &mut func_vec, &mut op_sig);
+    (op_idx_table, func_vec, op_sig)
+}
 
 #[test]
 fn parse_statement_lookup_op(){
     let t = vec![Tok::Sym(HashIdx::from_str("nop"))];
     let mut m = Mem::new();
     let mut c = Code::new();
-    let mut op_idx_table: AHashMap<&'static str, usize> = AHashMap::new();
-    let mut func_vec: Vec<super::OpFunc> = Vec::new();
-    super::init_op_table(&mut op_idx_table, &mut func_vec);
-    crate::preprocess(&op_idx_table, &mut m, &mut c, t).unwrap();
-    assert_eq!(super::exec(&mut func_vec, &mut m, &c).unwrap(), super::Signal::None);
+    let (op_idx_table, func_vec, op_sig) = op_table();
+    crate::interp::preprocess(&op_idx_table, &op_sig, &mut m, &mut c, t, LineSrc::default()).unwrap();
+    c.compile(&mut m, &op_sig).unwrap();
+    assert_eq!(super::exec(&func_vec, &mut m, &c).unwrap(), super::Signal::None);
+}
+
+#[test]
+fn preprocess_error_location(){
+    let mut m = Mem::new();
+    let mut c = Code::new();
+    let (op_idx_table, _, op_sig) = op_table();
+    c.set_file("a.lli");
+    let text = "  foo: 1".to_string();
+    let (t, cols) = tokenize_cols(&text).unwrap();
+    let src = LineSrc { line: 3, text, cols };
+    let r = crate::interp::preprocess(&op_idx_table, &op_sig, &mut m, &mut c, t, src).unwrap_err();
+    if let crate::Error::At(e, s) = r {
+        assert_matches!(*e, crate::Error::UnknownOp(_));
+        assert_eq!((s.file.as_str(), s.line, s.col), ("a.lli", 3, 2));
+    }else{
+        panic!("Error without location");
+    }
+}
+
+#[test]
+fn check_args(){
+    let sig: super::Sig = &[super::ArgKind::WPtr, super::ArgKind::Value];
+    let t = tokenize("mov: [1], $a").unwrap();
+    super::check_args(sig, &t[1..]).unwrap();
+    let t = tokenize("mov: \"asd\", 1").unwrap();
+    assert_matches!(super::check_args(sig, &t[1..]),
+        Err((crate::Error::WrongArgType(_, Tok::LTL_STR), 0)));
+    let t = tokenize("mov: [-1], 1").unwrap();
+    assert_matches!(super::check_args(sig, &t[1..]),
+        Err((crate::Error::WriteToNMem(-1), 0)));
+    let t = tokenize("mov: 1, 1").unwrap();
+    assert_matches!(super::check_args(sig, &t[1..]),
+        Err((crate::Error::WrongArgType(_, Tok::NUM_STR), 0)));
+    let t = tokenize("mov: [1]").unwrap();
+    assert_matches!(super::check_args(sig, &t[1..]),
+        Err((crate::Error::WrongArgCount(2, 1), 0)));
+}
+
+#[test]
+fn check_args_fmt(){
+    let sig: super::Sig = &[super::ArgKind::Value, super::ArgKind::Fmt];
+    let t = tokenize("printf: 1, \"%d %s\", [2], \"a\"").unwrap();
+    super::check_args(sig, &t[1..]).unwrap();
+    let t = tokenize("printf: 1, \"%d %d\", 1").unwrap();
+    assert_matches!(super::check_args(sig, &t[1..]),
+        Err((crate::Error::WrongArgCount(4, 3), 0)));
+    // %s takes ptr
+    let t = tokenize("printf: 1, \"%s\", 1").unwrap();
+    assert_matches!(super::check_args(sig, &t[1..]),
+        Err((crate::Error::WrongArgType(_, Tok::NUM_STR), 2)));
+    let t = tokenize("printf: 1, \"%q\"").unwrap();
+    assert_matches!(super::check_args(sig, &t[1..]),
+        Err((crate::Error::InvalidFormat(_), 1)));
+}
+
+#[test]
+fn preprocess_missing_label(){
+    let mut m = Mem::new();
+    let mut c = Code::new();
+    let (op_idx_table, _, op_sig) = op_table();
+    let t = tokenize("jc: 1").unwrap();
+    let r = crate::interp::preprocess(&op_idx_table, &op_sig, &mut m, &mut c, t, LineSrc::default());
+    assert_matches!(r, Err(crate::Error::At(..)));
+    let t = tokenize("jc: 1, 2").unwrap();
+    let r = crate::interp::preprocess(&op_idx_table, &op_sig, &mut m, &mut c, t, LineSrc::default());
+    assert_matches!(r, Err(crate::Error::At(..)));
+    assert_eq!(c.len(), 0);
 }
@@ -0,0 +1,204 @@
+use std::io::Write;
+use std::fmt::Write as _;
+use std::time::{Duration, Instant};
+use ahash::AHashMap;
+use num_traits::FromPrimitive;
+use crate::interp::{Interpreter, State};
+use crate::code::{Code, Arg};
+use crate::op::{Opcode, Signal};
+use crate::error::Error;
+
+// number of entries in each section of Text report
+const TOP: usize = 20;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Format{
+    Text,  // sections sorted by time, for reading
+    Tsv,  // kind, name, count, nanos sorted by name, for diffing
+}
+
+#[derive(Clone, Copy, Default, Debug)]
+struct Stat{
+    count: u64,
+    time: Duration,
+}
+
+#[derive(Clone, Default, Debug)]
+struct LineStat{
+    line: usize,  // source line, starts from 1
+    text: String,
+    stat: Stat,
+}
+
+// Runs Interpreter and counts executions and time per line and per op
+pub struct Profiler{
+    pub format: Format,
+    files: Vec<String>,
+    file_idx: AHashMap<String, usize>,
+    lines: AHashMap<(usize, usize), LineStat>,  // (file, line index) -> stat
+    ops: Vec<Stat>,
+    labels: AHashMap<usize, u64>,  // label -> times lbl op is executed
+    label_file: AHashMap<usize, usize>,  // label -> file it is defined in
+    scanned: Vec<usize>,  // file -> lines scanned for labels
+    jumps: AHashMap<(usize, usize), u64>,  // (file, line index) -> times jumped to
+}
+
+impl Default for Profiler{
+    fn default() -> Self {
+        Profiler::new()
+    }
+}
+
+impl Profiler{
+    pub fn new() -> Profiler{
+        Profiler {
+            format: Format::Text,
+            files: Vec::new(),
+            file_idx: AHashMap::new(),
+            lines: AHashMap::new(),
+            ops: Vec::new(),
+            labels: AHashMap::new(),
+            label_file: AHashMap::new(),
+            scanned: Vec::new(),
+            jumps: AHashMap::new(),
+        }
+    }
+
+    // Run until all lines are executed or exit op is executed.
+    // Returns exit code
+    pub fn run(&mut self, i: &mut Interpreter) -> Result<i32, Error>{
+        loop {
+            let (file, ptr, op) = match i.code() {
+                Some(c) => {
+                    let file = match self.file_idx.get(c.file()) {
+                        Some(f) => *f,
+                        None => {
+                            self.files.push(c.file().to_owned());
+                            self.file_idx.insert(c.file().to_owned(), self.files.len()-1);
+                            self.scanned.push(0);
+                            self.files.len()-1
+                        },
+                    };
+                    self.scan_labels(file, c);
+                    let line = self.lines.entry((file, c.ptr())).or_insert_with(|| {
+                        let s = c.span(c.ptr(), 0);
+                        LineStat { line: s.line, text: s.text, stat: Stat::default() }
+                    });
+                    line.stat.count += 1;
+                    (file, c.ptr(), c.curr().map(|inst| inst.op))
+                },
+                None => (0, 0, None),
+            };
+            let start = Instant::now();
+            let r = i.step();
+            let time = start.elapsed();
+            if let Some(op) = op {
+                if let Some(line) = self.lines.get_mut(&(file, ptr)) {
+                    line.stat.time += time;
+                }
+                if op >= self.ops.len() {
+                    self.ops.resize(op+1, Stat::default());
+                }
+                self.ops[op].count += 1;
+                self.ops[op].time += time;
+                match (&r, i.signal()) {
+                    (Ok(_), Signal::SetLbl(l)) =>
+                        *self.labels.entry(*l).or_insert(0) += 1,
+                    (Ok(_), Signal::Jmp(p)) | (Ok(_), Signal::Call(p)) =>
+                        *self.jumps.entry((file, *p)).or_insert(0) += 1,
+                    _ => (),
+                }
+            }
+            match r? {
+                State::Running => (),
+                State::Finished => return Ok(0),
+                State::Exited(exit_code) => return Ok(exit_code),
+            }
+        }
+    }
+
+    // Record file of labels defined in lines not seen yet
+    fn scan_labels(&mut self, file: usize, c: &Code){
+        for l in self.scanned[file]..c.len() {
+            let inst = match c.inst_at(l) {
+                Some(inst) => inst,
+                None => break,
+            };
+            match FromPrimitive::from_usize(inst.op) {
+                Some(Opcode::Lbl) | Some(Opcode::Als) => if let Some(Arg::Lbl(lbl)) = c.args(&inst).first() {
+                    self.label_file.insert(*lbl, file);
+                },
+                _ => (),
+            }
+            self.scanned[file] = l+1;
+        }
+    }
+
+    // Write report of everything executed so far
+    pub fn report(&self, i: &Interpreter, out: &mut dyn Write) -> Result<(), Error>{
+        let mut lines: Vec<(&(usize, usize), &LineStat)> = self.lines.iter().collect();
+        let mut ops: Vec<(&str, Stat)> = self.ops.iter().enumerate()
+            .filter(|(_, s)| s.count > 0)
+            .map(|(op, s)| (i.op_name(op).unwrap_or("?"), *s))
+            .collect();
+        // label is reached by executing lbl op or jumping to it.
+        // jumps are matched to labels by the file and line index they land on
+        let m = i.mem();
+        let mut labels: Vec<(&str, u64)> = m.label_hash.iter()
+            .map(|(name, l)| {
+                let jumped = self.label_file.get(l)
+                    .and_then(|f| m.label_find(*l).ok().map(|p| (*f, p)))
+                    .and_then(|p| self.jumps.get(&p))
+                    .copied()
+                    .unwrap_or(0);
+                (name.as_str(), self.labels.get(l).copied().unwrap_or(0) + jumped)
+            })
+            .filter(|(_, count)| *count > 0)
+            .collect();
+        let mut s = String::new();
+        match self.format {
+            Format::Text => {
+                lines.sort_by_key(|l| std::cmp::Reverse(l.1.stat.time));
+                ops.sort_by_key(|o| std::cmp::Reverse(o.1.time));
+                labels.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(b.0)));
+                s.push_str("Top lines:\n        count    time(ms)  line\n");
+                for ((f, _), l) in lines.iter().take(TOP) {
+                    let _ = writeln!(s, "{:>13} {:>11.3}  {}:{}: {}",
+                        l.stat.count, ms(l.stat.time), self.files[*f], l.line, l.text.trim());
+                }
+                s.push_str("Top labels reached:\n        count  label\n");
+                for (name, count) in labels.iter().take(TOP) {
+                    let _ = writeln!(s, "{:>13}  {}", count, name);
+                }
+                s.push_str("Ops:\n        count    time(ms)  op\n");
+                for (name, stat) in &ops {
+                    let _ = writeln!(s, "{:>13} {:>11.3}  {}", stat.count, ms(stat.time), name);
+                }
+            },
+            Format::Tsv => {
+                lines.sort_by_key(|((f, _), l)| (&self.files[*f], l.line));
+                ops.sort_by_key(|o| o.0);
+                labels.sort_by_key(|l| l.0);
+                s.push_str("kind\tname\tcount\tnanos\n");
+                for ((f, _), l) in &lines {
+                    let _ = writeln!(s, "line\t{}:{}\t{}\t{}",
+                        self.files[*f], l.line, l.stat.count, l.stat.time.as_nanos());
+                }
+                for (name, count) in &labels {
+                    let _ = writeln!(s, "label\t{}\t{}\t-", name, count);
+                }
+                for (name, stat) in &ops {
+                    let _ = writeln!(s, "op\t{}\t{}\t{}", name, stat.count, stat.time.as_nanos());
+                }
+            },
+        }
+        out.write_all(s.as_bytes()).and_then(|_| out.flush()).map_err(Error::IoError)
+    }
+}
+
+fn ms(d: Duration) -> f64{
+    d.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,68 @@
+use super::*;
+
+fn profile(text: &str) -> (Interpreter, Profiler){
+    let mut i = Interpreter::new();
+    i.mem_mut().pmem_allc(&[0.0]);
+    i.load_str("test", text).unwrap();
+    let mut p = Profiler::new();
+    p.run(&mut i).unwrap();
+    (i, p)
+}
+
+// loop 3 times
+const LOOP: &str = "mov: [1], 3\nlbl: L\nsub: [1], 1\nmov: [1], [0]\njc: [1], L\nexit: 0";
+
+#[test]
+fn counts(){
+    let (i, p) = profile(LOOP);
+    let mut out = Vec::new();
+    let mut p = p;
+    p.format = Format::Tsv;
+    p.report(&i, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    // drop nanos, they vary between runs
+    let rows: Vec<String> = out.lines()
+        .map(|l| l.rsplit_once('\t').unwrap().0.to_owned())
+        .collect();
+    assert_eq!(rows, vec![
+        "kind\tname\tcount",
+        "line\ttest:1\t1",
+        "line\ttest:2\t1",
+        "line\ttest:3\t3",
+        "line\ttest:4\t3",
+        "line\ttest:5\t3",
+        "line\ttest:6\t1",
+        "label\tL\t3",
+        "op\texit\t1",
+        "op\tjc\t3",
+        "op\tlbl\t1",
+        "op\tmov\t4",
+        "op\tsub\t3",
+    ]);
+}
+
+#[test]
+fn text_report(){
+    let (i, p) = profile(LOOP);
+    let mut out = Vec::new();
+    p.report(&i, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.starts_with("Top lines:\n"));
+    assert!(out.contains("Top labels reached:\n        count  label\n            3  L\n"));
+    assert!(out.contains("  test:3: sub: [1], 1\n"));
+}
+
+#[test]
+fn labels_per_file(){
+    // both jumps land on line index 2, in different files
+    let path = std::env::temp_dir().join("lli_profile_test_labels.lli");
+    std::fs::write(&path, "jmp: B\nlbl: B\nadd: [0], 1").unwrap();
+    let text = format!("jmp: A\nlbl: A\nsrc: {}", path.display());
+    let (i, mut p) = profile(&text);
+    std::fs::remove_file(&path).unwrap();
+    p.format = Format::Tsv;
+    let mut out = Vec::new();
+    p.report(&i, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("label\tA\t1\t-\nlabel\tB\t1\t-\n"));
+}
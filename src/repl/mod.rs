@@ -0,0 +1,136 @@
+use std::io::{BufRead, Write};
+use std::fmt::Write as _;
+use crate::interp::{Interpreter, State};
+use crate::debug::Debugger;
+use crate::error::Error;
+
+const HELP: &str = "\
+Statements are executed as they are entered, then [0] is printed.
+:print [n] | $var | [a..b]   print slot, var or slots a until b
+:pmem                        dump pmem
+:nmem                        dump nmem
+:vars                        list vars
+:labels                      list labels
+:src <file>                  load and execute file
+:quit                        leave
+";
+
+// Reads statements line by line and executes them against persistent memory
+pub struct Repl{
+    dbg: Debugger,  // used for inspecting memory
+}
+
+impl Default for Repl{
+    fn default() -> Self {
+        Repl::new()
+    }
+}
+
+impl Repl{
+    pub fn new() -> Repl{
+        Repl {
+            dbg: Debugger::new(),
+        }
+    }
+
+    // Read lines until :quit, exit op or end of input.
+    // Returns exit code
+    pub fn run(
+        &mut self,
+        i: &mut Interpreter,
+        input: &mut dyn BufRead,
+        output: &mut dyn Write
+    ) -> Result<i32, Error>
+    {
+        let mut out = String::new();
+        let mut exit_code = None;
+        while exit_code.is_none() {
+            out.push_str("> ");
+            if let Err(e) = output.write_all(out.as_bytes()).and_then(|_| output.flush()) {
+                return Err(Error::IoError(e));
+            }
+            out.clear();
+            let mut line = String::new();
+            match input.read_line(&mut line) {
+                Ok(0) => break,
+                Ok(_) => (),
+                Err(e) => return Err(Error::IoError(e)),
+            }
+            exit_code = self.command(i, &line, &mut out);
+        }
+        if let Err(e) = output.write_all(out.as_bytes()) {
+            return Err(Error::IoError(e));
+        }
+        Ok(exit_code.unwrap_or(0))
+    }
+
+    // Execute a statement or meta-command, appending its output to out.
+    // Returns exit code when the session ends
+    pub fn command(&mut self, i: &mut Interpreter, line: &str, out: &mut String) -> Option<i32>{
+        let line = line.trim();
+        if let Some(cmd) = line.strip_prefix(':') {
+            return self.meta(i, cmd, out);
+        }
+        if line.is_empty() {
+            return None;
+        }
+        self.eval(i, line, out)
+    }
+
+    fn eval(&mut self, i: &mut Interpreter, line: &str, out: &mut String) -> Option<i32>{
+        match i.eval(line) {
+            Ok(State::Exited(code)) => return Some(code),
+            Ok(_) => (),
+            Err(e) => e.print(1),
+        }
+        let _ = writeln!(out, "[0] = {}", i.mem_at(0).unwrap_or(0.0));
+        None
+    }
+
+    // Load and execute file like :src, appending [0] to out.
+    // Errors are returned instead of printed, so a failed start can be told apart.
+    // Returns exit code if the file exits
+    pub fn src(&mut self, i: &mut Interpreter, file: &str, out: &mut String) -> Result<Option<i32>, Error>{
+        if let State::Exited(code) = i.eval(&format!("src: {}", file))? {
+            return Ok(Some(code));
+        }
+        let _ = writeln!(out, "[0] = {}", i.mem_at(0).unwrap_or(0.0));
+        Ok(None)
+    }
+
+    fn meta(&mut self, i: &mut Interpreter, cmd: &str, out: &mut String) -> Option<i32>{
+        let mut words = cmd.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("print"), Some(_)) | (Some("labels"), None) => {
+                self.dbg.command(i, cmd, out);
+            },
+            (Some("pmem"), None) => {
+                let cmd = format!("print [0..{}]", i.mem().pmem_len());
+                self.dbg.command(i, &cmd, out);
+            },
+            (Some("nmem"), None) => {
+                // nmem starts from -1
+                let cmd = format!("print [-1..-{}]", i.mem().nmem_len());
+                self.dbg.command(i, &cmd, out);
+            },
+            (Some("vars"), None) => {
+                let m = i.mem();
+                let mut vars: Vec<(&String, &usize)> = m.var_hash.iter().collect();
+                vars.sort_by_key(|(_, v)| **v);
+                for (name, v) in vars {
+                    if let Ok(idx) = m.var_find(*v) {
+                        let _ = writeln!(out, "${} = [{}]", name, idx);
+                    }
+                }
+            },
+            (Some("src"), Some(file)) => return self.eval(i, &format!("src: {}", file), out),
+            (Some("quit"), None) => return Some(0),
+            (Some("help"), None) => out.push_str(HELP),
+            _ => out.push_str("Unknown command, try :help\n"),
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,69 @@
+use super::*;
+
+#[test]
+fn eval_prints_result(){
+    let mut i = Interpreter::new();
+    let mut r = Repl::new();
+    let mut out = String::new();
+    assert_eq!(r.command(&mut i, "add: 1, 2\n", &mut out), None);
+    assert_eq!(out, "[0] = 3\n");
+}
+
+#[test]
+fn vars_and_labels(){
+    let mut i = Interpreter::new();
+    i.mem_mut().pmem_allc(&[0.0; 3]);
+    let mut r = Repl::new();
+    let mut out = String::new();
+    for line in &["var: p, [1]", "mov: [1], 2", "mov: [2], 9", "lbl: L", "add: [$p], 1"] {
+        r.command(&mut i, line, &mut out);
+    }
+    assert_eq!(i.mem_at(0).unwrap(), 10.0);
+    out.clear();
+    r.command(&mut i, ":vars", &mut out);
+    assert_eq!(out, "$p = [1]\n");
+    out.clear();
+    r.command(&mut i, ":labels", &mut out);
+    assert_eq!(out, "L: line 5\n");
+    out.clear();
+    r.command(&mut i, ":pmem", &mut out);
+    assert_eq!(out, "[0] = 10\n[1] = 2\n[2] = 9\n[3] = 0\n[4] = 0\n[5] = 0\n[6] = 0\n");
+}
+
+#[test]
+fn error_does_not_stop(){
+    let mut i = Interpreter::new();
+    let mut r = Repl::new();
+    let mut out = String::new();
+    assert_eq!(r.command(&mut i, "mov: [9], 1", &mut out), None);
+    assert_eq!(r.command(&mut i, "jmp: Nowhere", &mut out), None);
+    out.clear();
+    r.command(&mut i, "add: 2, 2", &mut out);
+    assert_eq!(out, "[0] = 4\n");
+}
+
+#[test]
+fn run_until_exit(){
+    let mut i = Interpreter::new();
+    let mut r = Repl::new();
+    let mut input: &[u8] = b"add: 1, 1\n:nmem\nexit: 4\nadd: 1, 1\n";
+    let mut output = Vec::new();
+    assert_eq!(r.run(&mut i, &mut input, &mut output).unwrap(), 4);
+    assert_eq!(String::from_utf8(output).unwrap(), "> [0] = 2\n> > ");
+}
+
+#[test]
+fn src_file(){
+    let path = std::env::temp_dir().join("lli_repl_test_src.lli");
+    std::fs::write(&path, "add: 2, 3").unwrap();
+    let mut i = Interpreter::new();
+    let mut r = Repl::new();
+    let mut out = String::new();
+    assert_eq!(r.src(&mut i, path.to_str().unwrap(), &mut out).unwrap(), None);
+    assert_eq!(out, "[0] = 5\n");
+    // failure is returned, not only printed
+    std::fs::remove_file(&path).unwrap();
+    out.clear();
+    assert!(r.src(&mut i, path.to_str().unwrap(), &mut out).is_err());
+    assert_eq!(out, "");
+}
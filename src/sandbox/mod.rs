@@ -0,0 +1,103 @@
+use std::path::{Path, PathBuf};
+use crate::error::Error;
+
+// Filesystem policy for untrusted scripts, checked by open op and src.
+// Everything is denied until allowed. Paths are compared after
+// resolving symlinks, so links can't be used to escape a directory
+#[derive(Clone, Default, Debug)]
+pub struct Sandbox{
+    read: Vec<PathBuf>,  // open for reading
+    write: Vec<PathBuf>,  // open for reading and writing, except create_new and truncate
+    scratch: Vec<PathBuf>,  // anything, including create_new and truncate
+    root: Option<PathBuf>,  // src only loads files under root
+}
+
+impl Sandbox{
+    pub fn new() -> Sandbox{
+        Sandbox::default()
+    }
+
+    pub fn allow_read<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), Error>{
+        self.read.push(canonical_dir(dir.as_ref())?);
+        Ok(())
+    }
+
+    pub fn allow_write<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), Error>{
+        self.write.push(canonical_dir(dir.as_ref())?);
+        Ok(())
+    }
+
+    pub fn allow_scratch<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), Error>{
+        self.scratch.push(canonical_dir(dir.as_ref())?);
+        Ok(())
+    }
+
+    pub fn set_root<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), Error>{
+        self.root = Some(canonical_dir(dir.as_ref())?);
+        Ok(())
+    }
+
+    pub fn root(&self) -> Option<&Path>{
+        self.root.as_deref()
+    }
+
+    // write: file is opened for write or append, or may be created.
+    // replace: create_new or truncate, only allowed in scratch
+    pub fn check_open(&self, path: &str, write: bool, replace: bool) -> Result<(), Error>{
+        let p = resolve(Path::new(path))?;
+        let under = |dirs: &[PathBuf]| dirs.iter().any(|d| p.starts_with(d));
+        let ok = if replace {
+            under(&self.scratch)
+        }else if write {
+            under(&self.scratch) || under(&self.write)
+        }else{
+            under(&self.scratch) || under(&self.write) || under(&self.read)
+        };
+        if ok {
+            Ok(())
+        }else{
+            Err(Error::PermissionDenied(path.to_owned()))
+        }
+    }
+
+    pub fn check_src(&self, path: &str) -> Result<(), Error>{
+        let p = resolve(Path::new(path))?;
+        match &self.root {
+            Some(root) if p.starts_with(root) => Ok(()),
+            _ => Err(Error::PermissionDenied(path.to_owned())),
+        }
+    }
+}
+
+fn canonical_dir(dir: &Path) -> Result<PathBuf, Error>{
+    let d = dir.canonicalize().map_err(Error::IoError)?;
+    if !d.is_dir() {
+        return Err(Error::PermissionDenied(dir.to_string_lossy().into_owned()));
+    }
+    Ok(d)
+}
+
+// Absolute path with symlinks resolved.
+// File that doesn't exist yet is resolved through its parent.
+// Dangling link is denied, open would follow it when creating
+fn resolve(path: &Path) -> Result<PathBuf, Error>{
+    if let Ok(p) = path.canonicalize() {
+        return Ok(p);
+    }
+    if path.symlink_metadata().is_ok() {
+        return Err(Error::PermissionDenied(path.to_string_lossy().into_owned()));
+    }
+    let name = match path.file_name() {
+        Some(n) => n,
+        None => return Err(Error::PermissionDenied(path.to_string_lossy().into_owned())),
+    };
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let parent = parent.canonicalize().map_err(Error::IoError)?;
+    Ok(parent.join(name))
+}
+
+#[cfg(test)]
+mod test;
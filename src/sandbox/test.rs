@@ -0,0 +1,77 @@
+use std::fs;
+use std::path::PathBuf;
+use super::*;
+
+// fresh directory with ro, rw and tmp inside
+fn dirs(name: &str) -> PathBuf{
+    let d = std::env::temp_dir().join(name);
+    let _ = fs::remove_dir_all(&d);
+    for sub in &["ro", "rw", "tmp"] {
+        fs::create_dir_all(d.join(sub)).unwrap();
+    }
+    fs::write(d.join("ro/a"), "a").unwrap();
+    fs::write(d.join("secret"), "s").unwrap();
+    d
+}
+
+fn path(d: &Path, p: &str) -> String{
+    d.join(p).to_str().unwrap().to_owned()
+}
+
+#[test]
+fn check_open(){
+    let d = dirs("lli_sandbox_test_open");
+    let mut s = Sandbox::new();
+    s.allow_read(d.join("ro")).unwrap();
+    s.allow_write(d.join("rw")).unwrap();
+    s.allow_scratch(d.join("tmp")).unwrap();
+    s.check_open(&path(&d, "ro/a"), false, false).unwrap();
+    assert_matches!(s.check_open(&path(&d, "ro/a"), true, false), Err(Error::PermissionDenied(_)));
+    assert_matches!(s.check_open(&path(&d, "secret"), false, false), Err(Error::PermissionDenied(_)));
+    // .. is resolved
+    assert_matches!(s.check_open(&path(&d, "ro/../secret"), false, false),
+        Err(Error::PermissionDenied(_)));
+    // file to be created
+    s.check_open(&path(&d, "rw/new"), true, false).unwrap();
+    assert_matches!(s.check_open(&path(&d, "rw/new"), true, true), Err(Error::PermissionDenied(_)));
+    s.check_open(&path(&d, "tmp/new"), true, true).unwrap();
+    fs::remove_dir_all(&d).unwrap();
+}
+
+#[test]
+fn check_open_symlink(){
+    let d = dirs("lli_sandbox_test_symlink");
+    std::os::unix::fs::symlink(d.join("secret"), d.join("ro/link")).unwrap();
+    let mut s = Sandbox::new();
+    s.allow_read(d.join("ro")).unwrap();
+    assert_matches!(s.check_open(&path(&d, "ro/link"), false, false),
+        Err(Error::PermissionDenied(_)));
+    fs::remove_dir_all(&d).unwrap();
+}
+
+#[test]
+fn check_open_dangling_symlink(){
+    let d = dirs("lli_sandbox_test_dangling");
+    std::os::unix::fs::symlink(d.join("outside"), d.join("rw/link")).unwrap();
+    let mut s = Sandbox::new();
+    s.allow_write(d.join("rw")).unwrap();
+    s.set_root(d.join("rw")).unwrap();
+    assert_matches!(s.check_open(&path(&d, "rw/link"), true, false),
+        Err(Error::PermissionDenied(_)));
+    assert_matches!(s.check_src(&path(&d, "rw/link")), Err(Error::PermissionDenied(_)));
+    assert!(!d.join("outside").exists());
+    fs::remove_dir_all(&d).unwrap();
+}
+
+#[test]
+fn check_src(){
+    let d = dirs("lli_sandbox_test_src");
+    let mut s = Sandbox::new();
+    // no root, no src
+    assert_matches!(s.check_src(&path(&d, "ro/a")), Err(Error::PermissionDenied(_)));
+    s.set_root(d.join("ro")).unwrap();
+    s.check_src(&path(&d, "ro/a")).unwrap();
+    assert_matches!(s.check_src(&path(&d, "ro/../secret")), Err(Error::PermissionDenied(_)));
+    assert_matches!(s.allow_read(d.join("secret")), Err(Error::PermissionDenied(_)));
+    fs::remove_dir_all(&d).unwrap();
+}
@@ -0,0 +1,69 @@
+use std::io::{Read, Write};
+use std::fs::File;
+use std::rc::Rc;
+use std::cell::RefCell;
+
+// Object behind an lli fd, used by read, write and print ops
+pub enum Stream{
+    In(Box<dyn Read>),
+    Out(Box<dyn Write>),
+    File(File),  // opened by open op, may be read and written
+}
+
+impl Stream{
+    pub fn reader(&mut self) -> Option<&mut dyn Read>{
+        match self {
+            Stream::In(r) => Some(r.as_mut()),
+            Stream::File(f) => Some(f),
+            Stream::Out(_) => None,
+        }
+    }
+
+    pub fn writer(&mut self) -> Option<&mut dyn Write>{
+        match self {
+            Stream::Out(w) => Some(w.as_mut()),
+            Stream::File(f) => Some(f),
+            Stream::In(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Debug for Stream{
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Stream::In(_) => f.write_str("In"),
+            Stream::Out(_) => f.write_str("Out"),
+            Stream::File(file) => write!(f, "File({:?})", file),
+        }
+    }
+}
+
+// In-memory output shared with the embedder, e.g. to check script output in tests:
+//      let out = Capture::default();
+//      m.io_attach(1, Stream::Out(Box::new(out.clone())));
+#[derive(Clone, Default, Debug)]
+pub struct Capture(Rc<RefCell<Vec<u8>>>);
+
+impl Capture{
+    pub fn contents(&self) -> Vec<u8>{
+        self.0.borrow().clone()
+    }
+
+    pub fn to_string_lossy(&self) -> String{
+        String::from_utf8_lossy(&self.0.borrow()).into_owned()
+    }
+}
+
+impl Write for Capture{
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,15 @@
+use super::*;
+
+#[test]
+fn direction(){
+    let mut s = Stream::In(Box::new(&b"ab"[..]));
+    assert!(s.writer().is_none());
+    let mut buf = [0; 2];
+    s.reader().unwrap().read_exact(&mut buf).unwrap();
+    assert_eq!(&buf, b"ab");
+    let out = Capture::default();
+    let mut s = Stream::Out(Box::new(out.clone()));
+    assert!(s.reader().is_none());
+    s.writer().unwrap().write_all(b"cd").unwrap();
+    assert_eq!(out.contents(), b"cd");
+}
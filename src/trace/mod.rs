@@ -0,0 +1,104 @@
+use std::io::Write;
+use std::fmt::Write as _;
+use std::ops::RangeInclusive;
+use crate::interp::{Interpreter, State};
+use crate::code::Arg;
+use crate::mem::Mem;
+use crate::error::Error;
+
+// Runs Interpreter and logs every executed line:
+// file:line: op operands -> Signal
+pub struct Tracer{
+    pub lines: Option<RangeInclusive<usize>>,  // only trace these lines
+    pub ops: Vec<String>,  // only trace these ops, all if empty
+    out: Box<dyn Write>,
+}
+
+impl Tracer{
+    pub fn new(out: Box<dyn Write>) -> Tracer{
+        Tracer {
+            lines: None,
+            ops: Vec::new(),
+            out,
+        }
+    }
+
+    // Run until all lines are executed or exit op is executed.
+    // Returns exit code
+    pub fn run(&mut self, i: &mut Interpreter) -> Result<i32, Error>{
+        let r = self.trace(i);
+        // trace may be buffered
+        if let Err(e) = self.out.flush() {
+            return Err(Error::IoError(e));
+        }
+        r
+    }
+
+    fn trace(&mut self, i: &mut Interpreter) -> Result<i32, Error>{
+        loop {
+            let entry = self.entry(i);
+            let r = i.step();
+            if let Some(mut entry) = entry {
+                match r {
+                    Ok(_) => { let _ = writeln!(entry, " -> {:?}", i.signal()); },
+                    Err(_) => entry.push_str(" -> error\n"),
+                }
+                if let Err(e) = self.out.write_all(entry.as_bytes()) {
+                    return Err(Error::IoError(e));
+                }
+            }
+            match r? {
+                State::Running => (),
+                State::Finished => return Ok(0),
+                State::Exited(exit_code) => return Ok(exit_code),
+            }
+        }
+    }
+
+    // Describe the line to be executed, None if it is filtered out.
+    // Operands are resolved before execution
+    fn entry(&self, i: &Interpreter) -> Option<String>{
+        let code = i.code()?;
+        let inst = code.curr()?;
+        let span = code.span(code.ptr(), 0);
+        let name = i.op_name(inst.op).unwrap_or("?");
+        if let Some(lines) = &self.lines {
+            if !lines.contains(&span.line) {
+                return None;
+            }
+        }
+        if !self.ops.is_empty() && !self.ops.iter().any(|o| o == name) {
+            return None;
+        }
+        let mut s = format!("{}:{}: {}", span.file, span.line, name);
+        for (n, a) in code.args(&inst).iter().enumerate() {
+            s.push_str(if n == 0 { " " } else { ", " });
+            s.push_str(&fmt_arg(a, i.mem()));
+        }
+        Some(s)
+    }
+}
+
+// Num as is, slots as [loc]=value,
+// labels as name@n where n is the line index jumped to
+fn fmt_arg(a: &Arg, m: &Mem) -> String{
+    match *a {
+        Arg::Num(f) => f.to_string(),
+        Arg::Ltl(l) => format!("[{}]{:?}", l, m.read_ltl(l).unwrap_or_default()),
+        Arg::Lbl(l) => match m.label_find(l) {
+            Ok(line) => format!("{}@{}", m.label_name(l), line),
+            Err(_) => m.label_name(l),
+        },
+        Arg::Sym(s) => m.sym_at(s).to_owned(),
+        _ => match a.get_loc(m) {
+            Ok(loc) => match m.mem_at(loc) {
+                Ok(v) => format!("[{}]={}", loc, v),
+                Err(_) => format!("[{}]=?", loc),
+            },
+            Err(_) => "?".to_owned(),
+        },
+    }
+}
+
+#[cfg(test)]
+mod test;
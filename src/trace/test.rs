@@ -0,0 +1,61 @@
+use super::*;
+use std::rc::Rc;
+use std::cell::RefCell;
+
+// Write into a buffer that is still readable after being boxed
+#[derive(Clone, Default)]
+struct Buf(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Buf{
+    fn write(&mut self, b: &[u8]) -> std::io::Result<usize>{
+        self.0.borrow_mut().write(b)
+    }
+    fn flush(&mut self) -> std::io::Result<()>{
+        Ok(())
+    }
+}
+
+impl Buf{
+    fn text(&self) -> String{
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+fn trace(text: &str, f: impl FnOnce(&mut Tracer)) -> String{
+    let mut i = Interpreter::new();
+    i.mem_mut().pmem_allc(&[0.0; 2]);
+    i.load_str("test", text).unwrap();
+    let buf = Buf::default();
+    let mut t = Tracer::new(Box::new(buf.clone()));
+    f(&mut t);
+    t.run(&mut i).unwrap();
+    buf.text()
+}
+
+#[test]
+fn trace_all(){
+    let out = trace("mov: [1], 3\nadd: [1], 2\njmp: L\nlbl: L\nexit: [0]", |_| ());
+    assert_eq!(out, "test:1: mov [1]=0, 3 -> None\n\
+        test:2: add [1]=3, 2 -> None\n\
+        test:3: jmp L@4 -> Jmp(4)\n\
+        test:5: exit [0]=5 -> Exit(5)\n");
+}
+
+#[test]
+fn trace_filter(){
+    let text = "mov: [1], 3\nadd: [1], 2\nadd: [1], 2\nmov: [2], 1";
+    let out = trace(text, |t| t.lines = Some(2..=3));
+    assert_eq!(out, "test:2: add [1]=3, 2 -> None\ntest:3: add [1]=3, 2 -> None\n");
+    let out = trace(text, |t| t.ops = vec!["mov".to_owned()]);
+    assert_eq!(out, "test:1: mov [1]=0, 3 -> None\ntest:4: mov [2]=0, 1 -> None\n");
+}
+
+#[test]
+fn trace_error(){
+    let mut i = Interpreter::new();
+    i.load_str("test", "mov: [5], 1").unwrap();
+    let buf = Buf::default();
+    let mut t = Tracer::new(Box::new(buf.clone()));
+    assert_matches!(t.run(&mut i), Err(Error::At(..)));
+    assert_eq!(buf.text(), "test:1: mov [5]=?, 1 -> error\n");
+}